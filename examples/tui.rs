@@ -3,17 +3,23 @@ use egui_code_editor::{Syntax, Token, TokenType};
 
 fn color(token: TokenType) -> Color {
     match token {
+        TokenType::Attribute => Color::LightSlateBlue,
         TokenType::Comment(_) => Color::Grey37,
         TokenType::Function => Color::Yellow3b,
         TokenType::Keyword => Color::IndianRed1c,
+        TokenType::Lifetime => Color::IndianRed1a,
+        TokenType::Macro => Color::Yellow3a,
         TokenType::Literal => Color::NavajoWhite1,
         TokenType::Numeric(_) => Color::MediumPurple,
         TokenType::Punctuation(_) => Color::Orange3,
+        TokenType::Operator => Color::Orange1,
         TokenType::Special => Color::Cyan,
         TokenType::Str(_) => Color::Green,
+        TokenType::Escape => Color::SeaGreen2,
         TokenType::Type => Color::GreenYellow,
         TokenType::Whitespace(_) => Color::White,
         TokenType::Hyperlink => Color::Blue3b,
+        TokenType::Interpolation => Color::Cyan,
         TokenType::Unknown => Color::Pink1,
     }
 }
@@ -0,0 +1,120 @@
+use super::highlighting::Span;
+use super::syntax::{Syntax, TokenType};
+
+/// Pairs of opening and closing brackets.
+pub const BRACKETS: [[char; 2]; 3] = [['(', ')'], ['[', ']'], ['{', '}']];
+
+/// Char indices of brackets in `text`, skipping ones inside strings and comments.
+pub fn brackets<'a>(text: &'a str, syntax: &'a Syntax) -> impl Iterator<Item = (usize, char)> + 'a {
+    brackets_in(text, syntax.spans(text))
+}
+
+/// Char indices of brackets in punctuation `spans` of `text`,
+/// and in attribute openers like `#[`, so their closing bracket is matched.
+pub fn brackets_in<'a>(
+    text: &'a str,
+    spans: impl IntoIterator<Item = Span> + 'a,
+) -> impl Iterator<Item = (usize, char)> + 'a {
+    spans
+        .into_iter()
+        .filter(|span| matches!(span.ty, TokenType::Punctuation(_) | TokenType::Attribute))
+        .flat_map(move |span| {
+            span.slice(text)
+                .chars()
+                .enumerate()
+                .map(move |(i, c)| (span.chars.start + i, c))
+        })
+        .filter(|(_, c)| BRACKETS.iter().any(|pair| pair.contains(c)))
+}
+
+/// Char index of the bracket matching the one at `char_index`, if there is a bracket.
+pub fn matching_bracket(text: &str, syntax: &Syntax, char_index: usize) -> Option<usize> {
+    let mut after = brackets(text, syntax).skip_while(|(i, _)| *i < char_index);
+    let (_, bracket) = after.next().filter(|(i, _)| *i == char_index)?;
+    let [open, close] = *BRACKETS.iter().find(|pair| pair.contains(&bracket))?;
+    if bracket == open {
+        let mut depth = 0usize;
+        return after.find_map(|(i, c)| match c {
+            c if c == open => {
+                depth += 1;
+                None
+            }
+            c if c == close && depth == 0 => Some(i),
+            c if c == close => {
+                depth -= 1;
+                None
+            }
+            _ => None,
+        });
+    }
+    let mut opened = Vec::new();
+    for (i, c) in brackets(text, syntax).take_while(|(i, _)| *i < char_index) {
+        if c == open {
+            opened.push(i);
+        } else if c == close {
+            opened.pop();
+        }
+    }
+    opened.pop()
+}
+
+/// Char indices of brackets with their nesting depth, `None` for unbalanced ones.
+pub fn bracket_depths(
+    brackets: impl IntoIterator<Item = (usize, char)>,
+) -> Vec<(usize, Option<usize>)> {
+    let mut depths = Vec::new();
+    let mut opened: Vec<(usize, char)> = Vec::new();
+    for (i, c) in brackets {
+        let depth = if BRACKETS.iter().any(|[open, _]| *open == c) {
+            opened.push((depths.len(), c));
+            Some(opened.len() - 1)
+        } else if opened
+            .last()
+            .is_some_and(|(_, open)| BRACKETS.contains(&[*open, c]))
+        {
+            opened.pop();
+            Some(opened.len())
+        } else {
+            None
+        };
+        depths.push((i, depth));
+    }
+    for (unclosed, _) in opened {
+        depths[unclosed].1 = None;
+    }
+    depths
+}
+
+/// Char indices of every bracket with a match and of its match, sorted,
+/// the same as [`matching_bracket`] finds for each of them.
+pub fn bracket_pairs(brackets: impl IntoIterator<Item = (usize, char)>) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    let mut opened: [Vec<usize>; BRACKETS.len()] = Default::default();
+    for (i, c) in brackets {
+        let Some(kind) = BRACKETS.iter().position(|pair| pair.contains(&c)) else {
+            continue;
+        };
+        if BRACKETS[kind][0] == c {
+            opened[kind].push(i);
+        } else if let Some(open) = opened[kind].pop() {
+            pairs.extend([(open, i), (i, open)]);
+        }
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+#[cfg(feature = "egui")]
+#[derive(Default)]
+pub struct BracketPairs;
+
+#[cfg(feature = "egui")]
+impl egui::cache::ComputerMut<(&str, &Syntax), Vec<(usize, usize)>> for BracketPairs {
+    fn compute(&mut self, (text, syntax): (&str, &Syntax)) -> Vec<(usize, usize)> {
+        bracket_pairs(brackets(text, syntax))
+    }
+}
+
+#[cfg(feature = "egui")]
+/// [`bracket_pairs`] of a text, lexed again only when the text or the syntax changes.
+pub type BracketCache = egui::cache::FrameCache<Vec<(usize, usize)>, BracketPairs>;
@@ -1,6 +1,7 @@
 mod trie;
 
-use crate::{ColorTheme, Syntax, Token, TokenType, format_token};
+use crate::history::EditKind;
+use crate::{ColorTheme, Syntax, TokenType, format_token};
 use egui::{
     Event, Frame, Modifiers, Sense, Stroke, TextBuffer,
     text::{CCursor, CharIndex},
@@ -47,6 +48,7 @@ pub struct Completer {
     trie_user: Option<Trie>,
     variant_id: usize,
     completions: BTreeSet<String>,
+    edit: Option<EditKind>,
     pub text_edit_id: Option<egui::Id>,
 }
 
@@ -59,12 +61,19 @@ impl Completer {
         }
     }
     /// Completer will preserve indentation for next lines.
+    /// Overridden inside a [`CodeEditor`](crate::CodeEditor) with smart indentation.
     pub fn with_auto_indent(self) -> Self {
         Completer {
             indent: Some(String::new()),
             ..self
         }
     }
+    /// Stop preserving indentation, for editors indenting new lines themselves.
+    pub(crate) fn clear_indent(&mut self) {
+        if let Some(indent) = self.indent.as_mut() {
+            indent.clear();
+        }
+    }
     /// Completer will have second dictionary for words besides Syntax.
     pub fn with_user_words(self) -> Self {
         Completer {
@@ -87,7 +96,8 @@ impl Completer {
             ctx.input_mut(|i| {
                 if i.consume_key(Modifiers::NONE, egui::Key::Enter) {
                     i.events
-                        .push(Event::Paste(format!("\n{}", std::mem::take(indent))))
+                        .push(Event::Paste(format!("\n{}", std::mem::take(indent))));
+                    self.edit = Some(EditKind::Typing);
                 }
             });
         }
@@ -141,11 +151,17 @@ impl Completer {
                         .map(String::from)
                         .unwrap_or_default();
                     i.events.push(Event::Paste(completion));
+                    self.edit = Some(EditKind::Completion);
                 }
             });
         }
     }
 
+    /// Kind of the edit made by the last [`Completer::handle_input`], if any.
+    pub fn take_edit(&mut self) -> Option<EditKind> {
+        self.edit.take()
+    }
+
     /// If using Completer without CodeEditor this method should be called after text-editing widget as it uses &mut TextEditOutput
     pub fn show(
         &mut self,
@@ -171,11 +187,11 @@ impl Completer {
             // Update Completer Dictionary
             if let Some(trie_user) = self.trie_user.as_mut() {
                 trie_user.clear();
-                Token::default()
-                    .tokens(syntax, galley.text())
-                    .iter()
-                    .filter(|t| matches!(t.ty(), TokenType::Literal | TokenType::Function))
-                    .for_each(|t| trie_user.push(t.buffer()));
+                let text = galley.text();
+                syntax
+                    .spans(text)
+                    .filter(|s| matches!(s.ty, TokenType::Literal | TokenType::Function))
+                    .for_each(|s| trie_user.push(s.slice(text)));
             }
         }
 
@@ -0,0 +1,400 @@
+use crate::ColorTheme;
+use crate::hyperlinks::char_rects;
+use crate::search::Search;
+use egui::{
+    Event, Key, Modifiers, Stroke, TextBuffer,
+    text::{CCursor, CCursorRange, CharIndex},
+    text_edit::TextEditOutput,
+};
+use std::ops::Range;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Edit applied at every cursor.
+pub enum Edit {
+    /// Insert text, one line per cursor if their numbers match.
+    Insert(String),
+    Backspace,
+    Delete,
+}
+
+/// Selected char range of a cursor, `anchor..caret` in any order.
+fn sorted(cursor: &Range<usize>) -> Range<usize> {
+    cursor.start.min(cursor.end)..cursor.start.max(cursor.end)
+}
+
+/// Apply `edit` at every cursor of `text`, given as `anchor..caret` char ranges.
+/// Cursors collapse to carets after the edit and keep their order.
+pub fn apply_edit(text: &mut dyn TextBuffer, cursors: &mut [Range<usize>], edit: &Edit) {
+    let mut order: Vec<usize> = (0..cursors.len()).collect();
+    order.sort_by_key(|&i| sorted(&cursors[i]).start);
+    let lines: Vec<&str> = match edit {
+        Edit::Insert(inserted)
+            if inserted.lines().count() == cursors.len() && cursors.len() > 1 =>
+        {
+            inserted.lines().collect()
+        }
+        Edit::Insert(inserted) => vec![inserted.as_str(); cursors.len()],
+        _ => Vec::new(),
+    };
+    let mut delta = 0isize;
+    for (n, i) in order.into_iter().enumerate() {
+        let range = sorted(&cursors[i]);
+        let len = text.as_str().chars().count();
+        let start = range.start.saturating_add_signed(delta).min(len);
+        let end = range.end.saturating_add_signed(delta).min(len);
+        let removed = match edit {
+            Edit::Backspace if start == end => start.saturating_sub(1)..end,
+            Edit::Delete if start == end => start..(end + 1).min(len),
+            _ => start..end,
+        };
+        text.delete_char_range(CharIndex(removed.start)..CharIndex(removed.end));
+        let inserted = match lines.get(n) {
+            Some(line) => text.insert_text(line, CharIndex(removed.start)),
+            None => 0,
+        };
+        let caret = removed.start + inserted;
+        cursors[i] = caret..caret;
+        delta += inserted as isize - removed.len() as isize;
+    }
+}
+
+/// Line and column of the char at `char_index`.
+pub fn line_column(text: &str, char_index: usize) -> (usize, usize) {
+    let before: Vec<char> = text.chars().take(char_index).collect();
+    let line = before.iter().filter(|c| **c == '\n').count();
+    let column = before.iter().rev().take_while(|c| **c != '\n').count();
+    (line, column)
+}
+
+/// Width of a tab in columns, as egui lays it out.
+pub const TAB_WIDTH: usize = 4;
+
+/// Chars of `line` before visual `column`, tabs taking [`TAB_WIDTH`] columns.
+fn chars_before_column(line: &str, column: usize) -> usize {
+    let mut width = 0;
+    line.chars()
+        .take_while(|&c| {
+            width += match c {
+                '\t' => TAB_WIDTH,
+                _ => 1,
+            };
+            width <= column
+        })
+        .count()
+}
+
+/// Cursors of a box selection from `(line, column)` to another one, with visual columns,
+/// one per line with columns clamped to its end.
+pub fn box_selection(text: &str, from: (usize, usize), to: (usize, usize)) -> Vec<Range<usize>> {
+    let mut cursors = Vec::new();
+    let mut line_start = 0;
+    for (n, line) in text.split('\n').enumerate() {
+        if (from.0.min(to.0)..=from.0.max(to.0)).contains(&n) {
+            cursors.push(
+                line_start + chars_before_column(line, from.1)
+                    ..line_start + chars_before_column(line, to.1),
+            );
+        }
+        line_start += line.chars().count() + 1;
+    }
+    cursors
+}
+
+/// `anchor..caret` `cursor` moved by a navigation `key`, keeping the anchor to `extend` it.
+/// Left and Right collapse a selection to its side, Up and Down keep the char column.
+pub fn move_cursor(text: &str, cursor: Range<usize>, key: Key, extend: bool) -> Range<usize> {
+    let caret = cursor.end;
+    let selected = sorted(&cursor);
+    let (line, column) = line_column(text, caret);
+    let lines: Vec<&str> = text.split('\n').collect();
+    let line_start = |n: usize| -> usize { lines[..n].iter().map(|l| l.chars().count() + 1).sum() };
+    let at_column = |n: usize| line_start(n) + column.min(lines[n].chars().count());
+    let caret = match key {
+        Key::ArrowLeft if !extend && !selected.is_empty() => selected.start,
+        Key::ArrowRight if !extend && !selected.is_empty() => selected.end,
+        Key::ArrowLeft => caret.saturating_sub(1),
+        Key::ArrowRight => (caret + 1).min(text.chars().count()),
+        Key::ArrowUp if line > 0 => at_column(line - 1),
+        Key::ArrowUp => 0,
+        Key::ArrowDown if line + 1 < lines.len() => at_column(line + 1),
+        Key::ArrowDown => text.chars().count(),
+        Key::Home => line_start(line),
+        Key::End => line_start(line) + lines[line].chars().count(),
+        _ => caret,
+    };
+    match extend {
+        true => cursor.start..caret,
+        false => caret..caret,
+    }
+}
+
+/// Char range of the next occurrence of `needle` at char `after` or later, wrapping around
+/// and skipping ones already `taken`.
+pub fn next_occurrence(
+    text: &str,
+    needle: &str,
+    after: usize,
+    taken: &[Range<usize>],
+) -> Option<Range<usize>> {
+    let chars = |byte: usize| text[..byte].chars().count();
+    let occurrences: Vec<Range<usize>> = Search::new(needle)
+        .matches(text)
+        .ok()?
+        .into_iter()
+        .map(|m| chars(m.start)..chars(m.end))
+        .filter(|m| !taken.iter().any(|t| sorted(t) == *m))
+        .collect();
+    occurrences
+        .iter()
+        .find(|m| m.start >= after)
+        .or(occurrences.first())
+        .cloned()
+}
+
+/// Char range of the word around char `char_index`.
+fn word_at(text: &str, char_index: usize) -> Range<usize> {
+    let word = |c: &char| c.is_alphanumeric() || *c == '_';
+    let chars: Vec<char> = text.chars().collect();
+    let char_index = char_index.min(chars.len());
+    let start = char_index
+        - chars[..char_index]
+            .iter()
+            .rev()
+            .take_while(|c| word(c))
+            .count();
+    let end = char_index + chars[char_index..].iter().take_while(|c| word(c)).count();
+    start..end
+}
+
+#[derive(Clone, Debug, Default)]
+/// Cursors besides the primary one of the text editor.
+/// Ctrl+click adds one, Ctrl+D selects the next occurrence, Alt+drag makes a box selection
+/// and Escape or a click removes them. Arrows, Home and End, with Shift to select, move them all;
+/// other navigation like word jumps only moves the primary cursor.
+/// They are removed when the text changes elsewhere, see [`MultiCursor::sync`].
+pub struct MultiCursor {
+    /// Extra cursors as `anchor..caret` char ranges.
+    pub extra: Vec<Range<usize>>,
+    primary: Option<Range<usize>>,
+    text_edit_id: Option<egui::Id>,
+    /// Hash of the text the extra cursors are placed in.
+    text_hash: u64,
+}
+
+fn text_hash(text: &str) -> u64 {
+    use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher};
+    BuildHasherDefault::<DefaultHasher>::default().hash_one(text)
+}
+
+impl MultiCursor {
+    /// Remove extra cursors if `text` was changed since they were placed,
+    /// by the app, undo or replace, as they would point at the wrong chars.
+    pub fn sync(&mut self, text: &str) {
+        let hash = text_hash(text);
+        if hash != self.text_hash {
+            self.extra.clear();
+            self.text_hash = hash;
+        }
+    }
+
+    /// Extra cursors were moved along with an edit of `text`.
+    pub(crate) fn edited(&mut self, text: &str) {
+        self.text_hash = text_hash(text);
+    }
+
+    /// Should be called before the text editor, applies edits at every cursor.
+    pub fn handle_input(&mut self, ctx: &egui::Context, text: &mut dyn TextBuffer) {
+        self.primary = None;
+        self.sync(text.as_str());
+        // egui drops the focus on Escape before widgets see it, keep it for the primary cursor.
+        if let Some(id) = self.text_edit_id
+            && !self.extra.is_empty()
+            && ctx.memory(|m| m.had_focus_last_frame(id))
+            && ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Escape))
+        {
+            self.extra.clear();
+            ctx.memory_mut(|m| m.request_focus(id));
+        }
+        let Some(id) = self
+            .text_edit_id
+            .filter(|id| ctx.memory(|m| m.has_focus(*id)))
+        else {
+            return;
+        };
+        let Some(mut state) = egui::TextEdit::load_state(ctx, id) else {
+            return;
+        };
+        let Some(range) = state.cursor.char_range() else {
+            return;
+        };
+        let mut primary = range.secondary.index.0..range.primary.index.0;
+        let mut changed = false;
+        if ctx.input_mut(|i| i.consume_key(Modifiers::COMMAND, Key::D)) {
+            let selected = sorted(&primary);
+            if selected.is_empty() {
+                primary = word_at(text.as_str(), selected.start);
+            } else {
+                let needle = text.char_range(CharIndex(selected.start)..CharIndex(selected.end));
+                let taken: Vec<Range<usize>> =
+                    self.extra.iter().chain([&primary]).cloned().collect();
+                let after = taken
+                    .iter()
+                    .map(|t| sorted(t).end)
+                    .max()
+                    .unwrap_or_default();
+                if let Some(next) = next_occurrence(text.as_str(), needle, after, &taken) {
+                    self.extra.push(primary);
+                    primary = next;
+                }
+            }
+            changed = true;
+        }
+        if !self.extra.is_empty() {
+            let moves = ctx.input_mut(|i| {
+                let mut moves = Vec::new();
+                i.events.retain(|event| match event {
+                    Event::Key {
+                        key:
+                            key @ (Key::ArrowLeft
+                            | Key::ArrowRight
+                            | Key::ArrowUp
+                            | Key::ArrowDown
+                            | Key::Home
+                            | Key::End),
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } if modifiers.is_none() || *modifiers == Modifiers::SHIFT => {
+                        moves.push((*key, modifiers.shift));
+                        false
+                    }
+                    _ => true,
+                });
+                moves
+            });
+            for (key, extend) in moves {
+                for cursor in self.extra.iter_mut().chain([&mut primary]) {
+                    *cursor = move_cursor(text.as_str(), cursor.clone(), key, extend);
+                }
+                changed = true;
+            }
+            let edits = ctx.input_mut(|i| {
+                let mut edits = Vec::new();
+                i.events.retain(|event| {
+                    let edit = match event {
+                        Event::Text(text) | Event::Paste(text) => Edit::Insert(text.clone()),
+                        Event::Key {
+                            key,
+                            pressed: true,
+                            modifiers: Modifiers::NONE,
+                            ..
+                        } => match key {
+                            Key::Backspace => Edit::Backspace,
+                            Key::Delete => Edit::Delete,
+                            Key::Enter => Edit::Insert("\n".to_string()),
+                            _ => return true,
+                        },
+                        _ => return true,
+                    };
+                    edits.push(edit);
+                    false
+                });
+                edits
+            });
+            for edit in edits {
+                let mut cursors = std::mem::take(&mut self.extra);
+                cursors.push(primary);
+                apply_edit(text, &mut cursors, &edit);
+                primary = cursors.pop().unwrap_or_default();
+                self.extra = cursors;
+                changed = true;
+            }
+            self.extra.retain(|c| *c != primary);
+            self.extra.sort_by_key(|c| c.start);
+            self.extra.dedup();
+            self.edited(text.as_str());
+        }
+        if changed {
+            state.cursor.set_char_range(Some(CCursorRange::two(
+                CCursor::new(primary.start),
+                CCursor::new(primary.end),
+            )));
+            state.store(ctx, id);
+        }
+        self.primary = Some(primary);
+    }
+
+    /// Should be called after the text editor, adds cursors by mouse and paints them.
+    pub fn show(
+        &mut self,
+        ui: &egui::Ui,
+        output: &TextEditOutput,
+        theme: &ColorTheme,
+        fontsize: f32,
+    ) {
+        let response = &output.response.response;
+        self.text_edit_id = Some(response.id);
+        let current = output
+            .state
+            .cursor
+            .char_range()
+            .map(|r| r.secondary.index.0..r.primary.index.0);
+        let (pressed, modifiers, origin) = ui.input(|i| {
+            (
+                i.pointer.primary_pressed(),
+                i.modifiers,
+                i.pointer.press_origin(),
+            )
+        });
+        if pressed && response.hovered() {
+            match self.primary.take() {
+                Some(primary) if modifiers.command && current.as_ref() != Some(&primary) => {
+                    self.extra.push(primary);
+                }
+                _ if modifiers.command => {}
+                _ => self.extra.clear(),
+            }
+        }
+        if modifiers.alt
+            && response.dragged()
+            && let (Some(origin), Some(pointer)) = (origin, response.interact_pointer_pos())
+        {
+            let text = output.galley.text();
+            let width = ui.fonts_mut(|f| f.glyph_width(&egui::FontId::monospace(fontsize), ' '));
+            let corner = |pos: egui::Pos2| {
+                let pos = pos - output.galley_pos;
+                let (line, _) = line_column(text, output.galley.cursor_from_pos(pos).index.0);
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                (line, (pos.x / width).round().max(0.0) as usize)
+            };
+            let (from, to) = (corner(origin), corner(pointer));
+            let mut cursors = box_selection(text, from, to);
+            let primary = match to.0 < from.0 {
+                true => cursors.remove(0),
+                false => cursors.pop().unwrap_or_default(),
+            };
+            self.extra = cursors;
+            let mut state = output.state.clone();
+            state.cursor.set_char_range(Some(CCursorRange::two(
+                CCursor::new(primary.start),
+                CCursor::new(primary.end),
+            )));
+            state.store(ui.ctx(), response.id);
+        }
+
+        let painter = ui.painter().with_clip_rect(output.text_clip_rect);
+        for cursor in &self.extra {
+            for rect in char_rects(output, sorted(cursor)) {
+                painter.rect_filled(rect, 0.0, theme.selection().gamma_multiply(0.5));
+            }
+            let caret = output
+                .galley
+                .pos_from_cursor(CCursor::new(cursor.end))
+                .translate(output.galley_pos.to_vec2());
+            painter.line_segment(
+                [caret.center_top(), caret.center_bottom()],
+                Stroke::new(fontsize * 0.1, theme.cursor()),
+            );
+        }
+    }
+}
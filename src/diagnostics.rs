@@ -0,0 +1,134 @@
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// Severity of a [`Diagnostic`], the most severe first.
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl Severity {
+    /// Icon in the lines numbering.
+    pub fn icon(&self) -> char {
+        match self {
+            Severity::Error => '●',
+            Severity::Warning => '▲',
+            Severity::Info => '◆',
+            Severity::Hint => '○',
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// Message of an external checker about a char range of the text.
+pub struct Diagnostic {
+    /// Char range, an empty one marks the char at its start.
+    pub range: Range<usize>,
+    pub severity: Severity,
+    pub message: String,
+    /// Code of the rule, like `E0308`.
+    pub code: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new<S: Into<String>>(range: Range<usize>, severity: Severity, message: S) -> Self {
+        Diagnostic {
+            range,
+            severity,
+            message: message.into(),
+            code: None,
+        }
+    }
+    pub fn with_code<S: Into<String>>(self, code: S) -> Self {
+        Diagnostic {
+            code: Some(code.into()),
+            ..self
+        }
+    }
+
+    /// Char range to mark, at least one char long.
+    pub fn marked(&self) -> Range<usize> {
+        self.range.start..self.range.end.max(self.range.start + 1)
+    }
+
+    /// Does the diagnostic cover the char at `char_index`.
+    pub fn contains(&self, char_index: usize) -> bool {
+        self.marked().contains(&char_index)
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.code {
+            Some(code) => write!(f, "{:?}[{code}]: {}", self.severity, self.message),
+            None => write!(f, "{:?}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// Line index of the start of every diagnostic in `text`.
+pub fn diagnostic_lines(text: &str, diagnostics: &[Diagnostic]) -> Vec<usize> {
+    let newlines: Vec<usize> = text
+        .chars()
+        .enumerate()
+        .filter_map(|(i, c)| (c == '\n').then_some(i))
+        .collect();
+    diagnostics
+        .iter()
+        .map(|d| newlines.partition_point(|&n| n < d.range.start))
+        .collect()
+}
+
+/// The most severe diagnostic of every line of `text`, by line index.
+pub fn line_severities(text: &str, diagnostics: &[Diagnostic]) -> Vec<Option<Severity>> {
+    let mut severities = vec![None; text.matches('\n').count() + 1];
+    for (line, diagnostic) in diagnostic_lines(text, diagnostics)
+        .into_iter()
+        .zip(diagnostics)
+    {
+        let severity: &mut Option<Severity> = &mut severities[line];
+        *severity = Some(severity.map_or(diagnostic.severity, |s| s.min(diagnostic.severity)));
+    }
+    severities
+}
+
+#[cfg(feature = "egui")]
+/// Underline `diagnostics` in a `job` laid out from `text`, like the one of
+/// [`highlight`](crate::highlighting::highlight), with the `color` of their severity.
+/// Where diagnostics overlap the one starting first wins.
+///
+/// [`CodeEditor`](crate::CodeEditor) paints wavy squiggles over its galley instead,
+/// so its cached layout stays valid while diagnostics change.
+pub fn underline_diagnostics(
+    job: &mut egui::text::LayoutJob,
+    text: &str,
+    diagnostics: &[Diagnostic],
+    color: impl Fn(Severity) -> egui::Color32,
+) {
+    let bytes: Vec<usize> = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([text.len()])
+        .collect();
+    let byte = |char_index: usize| bytes.get(char_index).copied().unwrap_or(text.len());
+    let mut marked: Vec<(Range<usize>, Severity)> = diagnostics
+        .iter()
+        .map(|d| (byte(d.marked().start)..byte(d.marked().end), d.severity))
+        .collect();
+    marked.sort_by_key(|(range, severity)| (range.start, *severity));
+    let (mut ranges, mut severities): (Vec<Range<usize>>, Vec<Severity>) = (vec![], vec![]);
+    for (range, severity) in marked {
+        let start = range.start.max(ranges.last().map_or(0, |r| r.end));
+        if start < range.end {
+            ranges.push(start..range.end);
+            severities.push(severity);
+        }
+    }
+    crate::highlighting::restyle_ranges(job, &ranges, |i, format| {
+        format.underline = egui::Stroke::new(1.0, color(severities[i]));
+    });
+}
@@ -0,0 +1,140 @@
+use super::brackets::{BRACKETS, brackets};
+use super::syntax::Syntax;
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+/// Fold regions of `text` as line ranges, sorted by the first line, one per first line.
+///
+/// The first line of a region is its header and stays visible, the rest are hidden when folded.
+/// Regions come from brackets spanning several lines, blocks of deeper indentation
+/// and [`Syntax::fold_markers`].
+pub fn fold_regions(text: &str, syntax: &Syntax) -> Vec<Range<usize>> {
+    let mut regions = bracket_regions(text, syntax);
+    regions.extend(indent_regions(text));
+    regions.extend(marker_regions(text, syntax));
+    regions.retain(|r| r.end > r.start + 1);
+    regions.sort_by_key(|r| (r.start, usize::MAX - r.end));
+    regions.dedup_by_key(|r| r.start);
+    regions
+}
+
+/// Regions from the line of an opening bracket till the line of its closing one.
+fn bracket_regions(text: &str, syntax: &Syntax) -> Vec<Range<usize>> {
+    let newlines: Vec<usize> = text
+        .chars()
+        .enumerate()
+        .filter_map(|(i, c)| (c == '\n').then_some(i))
+        .collect();
+    let line = |i: usize| newlines.partition_point(|&n| n < i);
+    let mut opened = Vec::new();
+    let mut regions = Vec::new();
+    for (i, c) in brackets(text, syntax) {
+        if BRACKETS.iter().any(|[open, _]| *open == c) {
+            opened.push((i, c));
+        } else if let Some(&(open, bracket)) = opened.last()
+            && BRACKETS.contains(&[bracket, c])
+        {
+            opened.pop();
+            regions.push(line(open)..line(i));
+        }
+    }
+    regions
+}
+
+/// Regions from a line till the next one with the same or smaller indentation,
+/// without trailing blank lines.
+fn indent_regions(text: &str) -> Vec<Range<usize>> {
+    let indents: Vec<Option<usize>> = text
+        .lines()
+        .map(|line| {
+            let indent = line.len() - line.trim_start().len();
+            (indent < line.len()).then_some(indent)
+        })
+        .collect();
+    let mut regions = Vec::new();
+    for (start, indent) in indents.iter().enumerate() {
+        let Some(indent) = indent else {
+            continue;
+        };
+        let end = indents[start + 1..]
+            .iter()
+            .position(|i| i.is_some_and(|i| i <= *indent))
+            .map_or(indents.len(), |n| start + 1 + n);
+        let end = indents[..end]
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(end, |n| n + 1);
+        regions.push(start..end);
+    }
+    regions
+}
+
+/// Regions from a line with an opening marker till the line with its closing one.
+fn marker_regions(text: &str, syntax: &Syntax) -> Vec<Range<usize>> {
+    let mut opened: Vec<(usize, &str)> = Vec::new();
+    let mut regions = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim_start();
+        let uncommented = syntax
+            .comments()
+            .filter(|m| !m.is_empty())
+            .find_map(|m| line.strip_prefix(m))
+            .map(str::trim_start);
+        let marks = |marker: &str| {
+            !marker.is_empty()
+                && (line.starts_with(marker) || uncommented.is_some_and(|l| l.starts_with(marker)))
+        };
+        if let Some(index) = opened.iter().rposition(|(_, close)| marks(close)) {
+            regions.push(opened[index].0..n + 1);
+            opened.truncate(index);
+        } else if let Some([_, close]) = syntax.fold_markers.iter().find(|[open, _]| marks(open)) {
+            opened.push((n, close));
+        }
+    }
+    regions
+}
+
+#[cfg(feature = "egui")]
+/// Collapse rows of `hidden` byte ranges of `job` to zero height, keeping their text.
+pub fn hide_ranges(job: &mut egui::text::LayoutJob, hidden: &[Range<usize>]) {
+    super::highlighting::restyle_ranges(job, hidden, |_, format| {
+        format.color = egui::Color32::TRANSPARENT;
+        format.background = egui::Color32::TRANSPARENT;
+        format.line_height = Some(0.0);
+    });
+}
+
+/// Hidden line ranges of `folded` header lines, merged and sorted.
+pub fn hidden_lines(regions: &[Range<usize>], folded: &BTreeSet<usize>) -> Vec<Range<usize>> {
+    let mut hidden: Vec<Range<usize>> = Vec::new();
+    for region in regions.iter().filter(|r| folded.contains(&r.start)) {
+        match hidden.last_mut() {
+            Some(last) if region.start < last.end => last.end = last.end.max(region.end),
+            _ => hidden.push(region.start + 1..region.end),
+        }
+    }
+    hidden
+}
+
+/// Byte ranges of line ranges in `text`, each with the line breaks of its lines.
+pub fn line_bytes(text: &str, lines: &[Range<usize>]) -> Vec<Range<usize>> {
+    let starts: Vec<usize> = std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let start = |line: usize| starts.get(line).copied().unwrap_or(text.len());
+    lines.iter().map(|l| start(l.start)..start(l.end)).collect()
+}
+
+#[cfg(feature = "egui")]
+#[derive(Default)]
+pub struct Folder;
+
+#[cfg(feature = "egui")]
+impl egui::cache::ComputerMut<(&str, &Syntax), Vec<Range<usize>>> for Folder {
+    fn compute(&mut self, (text, syntax): (&str, &Syntax)) -> Vec<Range<usize>> {
+        fold_regions(text, syntax)
+    }
+}
+
+#[cfg(feature = "egui")]
+pub type FoldCache = egui::cache::FrameCache<Vec<Range<usize>>, Folder>;
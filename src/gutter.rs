@@ -0,0 +1,16 @@
+use egui::Color32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Icon in the lines numbering, like an execution arrow of a debugger.
+pub struct GutterIcon {
+    /// Line index, starting from zero.
+    pub line: usize,
+    pub icon: char,
+    pub color: Color32,
+}
+
+impl GutterIcon {
+    pub fn new(line: usize, icon: char, color: Color32) -> Self {
+        GutterIcon { line, icon, color }
+    }
+}
@@ -1,11 +1,11 @@
 #[cfg(feature = "editor")]
 use super::Editor;
 use super::syntax::{SEPARATORS, Syntax, TokenType};
-use std::{mem, ops::Range};
+use std::{collections::VecDeque, ops::Range, str::Chars};
 pub type Links = Vec<Range<usize>>;
 
-#[derive(Default, Debug, PartialEq, PartialOrd, Eq, Ord)]
-/// Lexer and Token
+#[derive(Default, Debug, Clone, PartialEq, PartialOrd, Eq, Ord)]
+/// Token with its own copy of the source text
 pub struct Token {
     ty: TokenType,
     buffer: String,
@@ -25,158 +25,368 @@ impl Token {
         &self.buffer
     }
 
-    fn first(&mut self, c: char, syntax: &Syntax) -> Option<Self> {
-        self.buffer.push(c);
-        let mut token = None;
+    #[cfg(feature = "egui")]
+    /// Syntax highlighting
+    pub fn highlight<T: Editor>(
+        &mut self,
+        editor: &T,
+        text: &str,
+        syntax: &Syntax,
+    ) -> (LayoutJob, Links) {
+        layout(editor, text, syntax.inject(text, syntax.spans(text)))
+    }
+
+    /// Lexer
+    pub fn tokens(&mut self, syntax: &Syntax, text: &str) -> Vec<Self> {
+        syntax
+            .inject(text, syntax.spans(text))
+            .into_iter()
+            .map(|span| Token::new(span.ty, span.slice(text)))
+            .collect()
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+/// Token type with its byte and char ranges in the source text
+pub struct Span {
+    pub ty: TokenType,
+    pub bytes: Range<usize>,
+    pub chars: Range<usize>,
+}
+
+impl Span {
+    /// Part of the source text covered by this span.
+    pub fn slice<'a>(&self, text: &'a str) -> &'a str {
+        &text[self.bytes.clone()]
+    }
+
+    fn shift(&mut self, bytes: isize, chars: isize) {
+        self.bytes =
+            self.bytes.start.wrapping_add_signed(bytes)..self.bytes.end.wrapping_add_signed(bytes);
+        self.chars =
+            self.chars.start.wrapping_add_signed(chars)..self.chars.end.wrapping_add_signed(chars);
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+/// Lexer state: type and bounds of the pending token.
+///
+/// The pending token is always a slice of the source text, so the lexer never copies it.
+pub struct Lexer {
+    ty: TokenType,
+    start: usize,
+    start_char: usize,
+    end: usize,
+    end_char: usize,
+    /// Index of multiline comment delimiters in [`Syntax::comments_multiline`].
+    comment: usize,
+    /// Depth of nested multiline comments.
+    depth: usize,
+    /// Length of the pending token already matched by comment markers,
+    /// or the string opening delimiter and escaped chars.
+    marker: usize,
+    /// Index of string delimiters in [`Syntax::delimiters`], `None` for simple quotes.
+    quote: Option<usize>,
+    /// Count of repeated chars in string delimiters.
+    repeat: usize,
+    /// Previous char is an escape char.
+    escaped: bool,
+    /// Range of heredoc word in the pending token.
+    word: Range<usize>,
+    /// Count of chars already lexed ahead.
+    skip: usize,
+    /// Strings interrupted by interpolated code, innermost last.
+    interpolations: Vec<Interpolating>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+/// String state to resume after interpolated code.
+struct Interpolating {
+    q: char,
+    quote: Option<usize>,
+    repeat: usize,
+    /// Byte position of the closing delimiter.
+    end: usize,
+    /// Length of the closing delimiter.
+    close: usize,
+}
+
+impl Interpolating {
+    /// Same state with the closing delimiter position relative to byte `end`.
+    fn relative(&self, end: usize) -> Interpolating {
+        Interpolating {
+            end: self.end.wrapping_sub(end),
+            ..self.clone()
+        }
+    }
+}
+
+impl Lexer {
+    fn buffer<'a>(&self, text: &'a str) -> &'a str {
+        &text[self.start..self.end]
+    }
+
+    /// Same type and same pending text, so lexing goes on identically.
+    fn resumes(&self, text: &str, other: &Lexer, other_text: &str) -> bool {
+        self.ty == other.ty
+            && self.comment == other.comment
+            && self.depth == other.depth
+            && self.marker == other.marker
+            && self.quote == other.quote
+            && self.repeat == other.repeat
+            && self.escaped == other.escaped
+            && self.word == other.word
+            && self.skip == other.skip
+            && self.interpolations.len() == other.interpolations.len()
+            && self
+                .interpolations
+                .iter()
+                .zip(&other.interpolations)
+                .all(|(a, b)| a.relative(self.end) == b.relative(other.end))
+            && self.buffer(text) == other.buffer(other_text)
+    }
+
+    fn shift(&mut self, bytes: isize, chars: isize) {
+        self.start = self.start.wrapping_add_signed(bytes);
+        self.end = self.end.wrapping_add_signed(bytes);
+        self.start_char = self.start_char.wrapping_add_signed(chars);
+        self.end_char = self.end_char.wrapping_add_signed(chars);
+        for i in &mut self.interpolations {
+            i.end = i.end.wrapping_add_signed(bytes);
+        }
+    }
+
+    /// The last line of the pending heredoc is its word.
+    fn heredoc_closed(&self, text: &str) -> bool {
+        let buffer = self.buffer(text);
+        buffer.rfind('\n').is_some_and(|i| {
+            buffer[i + 1..].trim_start_matches('\t').trim_end() == &buffer[self.word.clone()]
+        })
+    }
+
+    fn push(&mut self, c: char) {
+        self.end += c.len_utf8();
+        self.end_char += 1;
+    }
+
+    fn first(&mut self, c: char, syntax: &Syntax, text: &str) -> Option<Span> {
+        self.push(c);
+        if let Some((len, ty)) = syntax.pattern_at(text, self.start) {
+            return self.extend(text, len, ty);
+        }
+        if !c.is_whitespace()
+            && let Some((multiline, comment, marker)) = syntax.comment_at(&text[self.start..])
+        {
+            self.ty = TokenType::Comment(multiline);
+            self.comment = comment;
+            self.marker = marker;
+            return None;
+        }
+        if !c.is_whitespace()
+            && let Some((quote, opening)) = syntax.string_at(&text[self.start..])
+        {
+            self.ty = TokenType::Str(syntax.delimiters().nth(quote).map_or(c, |d| d.quote()));
+            self.quote = Some(quote);
+            self.repeat = opening.repeat;
+            self.word = opening.word;
+            self.marker = opening.len;
+            return None;
+        }
+        if let Some(len) = syntax.lifetime_at(&text[self.start..]) {
+            return self.extend(text, len, TokenType::Lifetime);
+        }
+        if let Some(len) = syntax.attribute_at(&text[self.start..]) {
+            return self.extend(text, len, TokenType::Attribute);
+        }
+        if let Some(len) = syntax.operator_at(&text[self.start..]) {
+            return self.extend(text, len, TokenType::Operator);
+        }
+        if let Some((len, float)) = syntax.numbers.len_at(&text[self.start..]) {
+            return self.extend(text, len, TokenType::Numeric(float));
+        }
+        if c.is_ascii_digit() && self.end - c.len_utf8() == self.start {
+            // Not a number, like `0xg` or `1e`: a plain word.
+            let word = &text[self.start..];
+            let len = word
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(word.len());
+            return self.extend(text, len, TokenType::Literal);
+        }
+        let mut span = None;
         self.ty = match c {
             c if c.is_whitespace() => {
                 self.ty = TokenType::Whitespace(c);
-                token = self.drain(self.ty);
+                span = self.drain(self.ty);
                 TokenType::Whitespace(c)
             }
             c if syntax.quotes.contains(&c) => TokenType::Str(c),
             c if syntax.is_keyword(c.to_string().as_str()) => TokenType::Keyword,
             c if syntax.is_type(c.to_string().as_str()) => TokenType::Type,
             c if syntax.is_special(c.to_string().as_str()) => TokenType::Special,
-            c if syntax.comment == c.to_string().as_str() => TokenType::Comment(false),
-            c if syntax.comment_multiline[0] == c.to_string().as_str() => TokenType::Comment(true),
             _ => TokenType::from((c, syntax)),
         };
-        token
+        span
     }
 
-    fn drain(&mut self, ty: TokenType) -> Option<Self> {
-        let mut token = None;
-        if !self.buffer().is_empty() {
-            token = Some(Token {
-                buffer: mem::take(&mut self.buffer),
+    /// Emit the pending token, started by the current char, extended to `len` bytes.
+    fn extend(&mut self, text: &str, len: usize, ty: TokenType) -> Option<Span> {
+        let chars = text[self.start..self.start + len].chars().count();
+        self.end = self.start + len;
+        self.end_char = self.start_char + chars;
+        self.skip = chars.saturating_sub(1);
+        self.ty = ty;
+        self.drain(TokenType::Unknown)
+    }
+
+    fn drain(&mut self, ty: TokenType) -> Option<Span> {
+        let mut span = None;
+        if self.start < self.end {
+            span = Some(Span {
                 ty: self.ty,
+                bytes: self.start..self.end,
+                chars: self.start_char..self.end_char,
             });
         }
+        self.start = self.end;
+        self.start_char = self.end_char;
+        self.comment = 0;
+        self.depth = 0;
+        self.marker = 0;
+        self.quote = None;
+        self.repeat = 0;
+        self.escaped = false;
+        self.word = 0..0;
         self.ty = ty;
-        token
+        span
     }
 
-    fn push_drain(&mut self, c: char, ty: TokenType) -> Option<Self> {
-        self.buffer.push(c);
+    /// Emit the next `len` bytes, starting with the current char, as a token of type `ty`.
+    fn jump(&mut self, text: &str, len: usize, ty: TokenType, spans: &mut impl Extend<Span>) {
+        spans.extend(self.drain(self.ty));
+        let chars = text[self.end..self.end + len].chars().count();
+        self.end += len;
+        self.end_char += chars;
+        self.skip = chars.saturating_sub(1);
+        self.ty = ty;
+        spans.extend(self.drain(TokenType::Unknown));
+    }
+
+    fn push_drain(&mut self, c: char, ty: TokenType) -> Option<Span> {
+        self.push(c);
         self.drain(ty)
     }
 
-    fn drain_push(&mut self, c: char, ty: TokenType) -> Option<Self> {
-        let token = self.drain(self.ty);
-        self.buffer.push(c);
+    fn drain_push(&mut self, c: char, ty: TokenType) -> Option<Span> {
+        let span = self.drain(self.ty);
+        self.push(c);
         self.ty = ty;
-        token
+        span
     }
 
-    #[cfg(feature = "egui")]
-    /// Syntax highlighting
-    pub fn highlight<T: Editor>(
-        &mut self,
-        editor: &T,
-        text: &str,
-        syntax: &Syntax,
-    ) -> (LayoutJob, Links) {
-        *self = Token::default();
-        let mut job = LayoutJob::default();
-        let mut links = Links::new();
-        let mut i: usize = 0;
-        for token in self.tokens(syntax, text) {
-            i += token.buffer().chars().count();
-            if token.ty() == TokenType::Hyperlink {
-                links.push(i.saturating_sub(token.buffer().chars().count())..i);
+    /// Feed the next char of `text` to the lexer.
+    fn automata(&mut self, c: char, syntax: &Syntax, text: &str, spans: &mut impl Extend<Span>) {
+        use TokenType as Ty;
+        if self.skip > 0 {
+            self.skip -= 1;
+            return;
+        }
+        if self
+            .interpolations
+            .last()
+            .is_some_and(|i| i.end == self.end)
+        {
+            let Some(i) = self.interpolations.pop() else {
+                return;
+            };
+            spans.extend(self.drain(Ty::Unknown));
+            if i.close > 0 {
+                self.jump(text, i.close, Ty::Interpolation, spans);
+            }
+            self.ty = Ty::Str(i.q);
+            self.quote = i.quote;
+            self.repeat = i.repeat;
+            if i.close == 0 {
+                self.automata(c, syntax, text, spans);
             }
-            editor.append(&mut job, &token);
+            return;
         }
-
-        // editor.append(&mut job, self);
-        (job, links)
-    }
-
-    /// Lexer
-    pub fn tokens(&mut self, syntax: &Syntax, text: &str) -> Vec<Self> {
-        let mut tokens: Vec<Self> = text
-            .chars()
-            .flat_map(|c| self.automata(c, syntax))
-            .collect();
-
-        if !self.buffer.is_empty() {
-            tokens.push(mem::take(self));
+        if Some(c) == syntax.macro_suffix
+            && matches!(self.ty, Ty::Literal | Ty::Keyword | Ty::Type | Ty::Special)
+            && !text[self.end + c.len_utf8()..].starts_with('=')
+        {
+            self.ty = Ty::Macro;
+            spans.extend(self.push_drain(c, Ty::Unknown));
+            return;
         }
-        tokens
-    }
-
-    fn automata(&mut self, c: char, syntax: &Syntax) -> Vec<Self> {
-        use TokenType as Ty;
-        let mut tokens = vec![];
         match (self.ty, Ty::from((c, syntax))) {
             (Ty::Comment(false), Ty::Whitespace('\n')) => {
-                self.buffer.push(c);
-                let n = self.buffer.pop();
-                tokens.extend(self.drain(Ty::Whitespace(c)));
-                if let Some(n) = n {
-                    tokens.extend(self.push_drain(n, self.ty));
-                }
+                spans.extend(self.drain(Ty::Whitespace(c)));
+                spans.extend(self.push_drain(c, self.ty));
             }
             (Ty::Comment(false), _) => {
-                self.buffer.push(c);
+                self.push(c);
             }
             (Ty::Comment(true), _) => {
-                self.buffer.push(c);
-                if self.buffer.ends_with(syntax.comment_multiline[1]) {
-                    tokens.extend(self.drain(Ty::Unknown));
+                self.push(c);
+                let Some([open, close]) = syntax.comments_multiline().nth(self.comment) else {
+                    return;
+                };
+                let buffer = self.buffer(text);
+                let unmatched = buffer.get(self.marker..).unwrap_or_default();
+                if unmatched.ends_with(close) {
+                    if self.depth == 0 {
+                        spans.extend(self.drain(Ty::Unknown));
+                    } else {
+                        self.depth -= 1;
+                        self.marker = buffer.len();
+                    }
+                } else if syntax.nested_comments && unmatched.ends_with(open) {
+                    self.depth += 1;
+                    self.marker = buffer.len();
                 }
             }
             (Ty::Literal | Ty::Punctuation(_), Ty::Whitespace(_)) => {
-                tokens.extend(self.drain(Ty::Whitespace(c)));
-                tokens.extend(self.first(c, syntax));
+                spans.extend(self.drain(Ty::Whitespace(c)));
+                spans.extend(self.first(c, syntax, text));
             }
             (Ty::Hyperlink, Ty::Whitespace(_)) => {
-                tokens.extend(self.drain(Ty::Whitespace(c)));
-                tokens.extend(self.first(c, syntax));
+                spans.extend(self.drain(Ty::Whitespace(c)));
+                spans.extend(self.first(c, syntax, text));
             }
             (Ty::Hyperlink, _) => {
-                self.buffer.push(c);
+                self.push(c);
             }
             (Ty::Literal, Ty::Punctuation(c)) => match c {
                 c if c == '(' => {
                     self.ty = Ty::Function;
-                    tokens.extend(self.drain(Ty::Punctuation(c)));
-                    tokens.extend(self.push_drain(c, Ty::Unknown));
+                    spans.extend(self.drain(Ty::Punctuation(c)));
+                    spans.extend(self.push_drain(c, Ty::Unknown));
                 }
-                c if syntax.is_hyperlink(&format!("{}{c}", self.buffer())) => {
+                c if syntax.is_hyperlink(&format!("{}{c}", self.buffer(text))) => {
                     self.ty = Ty::Hyperlink;
-                    self.buffer.push(c);
+                    self.push(c);
                 }
                 c if !c.is_alphanumeric()
                     && !SEPARATORS.contains(&c)
                     && !syntax.is_word_start(&c) =>
                 {
-                    tokens.extend(self.drain(self.ty));
-                    self.buffer.push(c);
-                    self.ty = if syntax.quotes.contains(&c) {
-                        Ty::Str(c)
-                    } else {
-                        Ty::Punctuation(c)
-                    };
+                    spans.extend(self.drain(self.ty));
+                    spans.extend(self.first(c, syntax, text));
                 }
                 _ => {
-                    self.buffer.push(c);
+                    self.push(c);
                     self.ty = Ty::Punctuation(c);
                 }
             },
             (Ty::Literal, _) => {
-                self.buffer.push(c);
+                self.push(c);
+                let buffer = self.buffer(text);
                 self.ty = {
-                    if self.buffer.starts_with(syntax.comment) {
-                        Ty::Comment(false)
-                    } else if self.buffer.starts_with(syntax.comment_multiline[0]) {
-                        Ty::Comment(true)
-                    } else if syntax.is_keyword(&self.buffer) {
+                    if syntax.is_keyword(buffer) {
                         Ty::Keyword
-                    } else if syntax.is_type(&self.buffer) {
+                    } else if syntax.is_type(buffer) {
                         Ty::Type
-                    } else if syntax.is_special(&self.buffer) {
+                    } else if syntax.is_special(buffer) {
                         Ty::Special
                     } else {
                         Ty::Literal
@@ -184,59 +394,96 @@ impl Token {
                 };
             }
             (Ty::Numeric(false), Ty::Punctuation('.')) => {
-                self.buffer.push(c);
+                self.push(c);
                 self.ty = Ty::Numeric(true);
             }
             (Ty::Numeric(_), Ty::Numeric(_)) => {
-                self.buffer.push(c);
+                self.push(c);
             }
             (Ty::Numeric(_), Ty::Literal) => {
-                tokens.extend(self.drain(self.ty));
-                self.buffer.push(c);
+                spans.extend(self.drain(self.ty));
+                self.push(c);
             }
             (Ty::Numeric(_), _) | (Ty::Punctuation(_), Ty::Literal | Ty::Numeric(_)) => {
-                tokens.extend(self.drain(self.ty));
-                tokens.extend(self.first(c, syntax));
+                spans.extend(self.drain(self.ty));
+                spans.extend(self.first(c, syntax, text));
             }
             (Ty::Punctuation(_), Ty::Str(_)) => {
-                tokens.extend(self.drain_push(c, Ty::Str(c)));
+                spans.extend(self.drain_push(c, Ty::Str(c)));
             }
             (Ty::Punctuation(_), _) => {
-                if !(syntax.comment.starts_with(&self.buffer)
-                    || syntax.comment_multiline[0].starts_with(&self.buffer))
-                {
-                    tokens.extend(self.drain(self.ty));
-                    tokens.extend(self.first(c, syntax));
-                } else {
-                    self.buffer.push(c);
-                    if self.buffer.starts_with(syntax.comment) {
-                        self.ty = Ty::Comment(false);
-                    } else if self.buffer.starts_with(syntax.comment_multiline[0]) {
-                        self.ty = Ty::Comment(true);
-                    } else if let Some(c) = self.buffer.pop() {
-                        tokens.extend(self.drain(Ty::Punctuation(c)));
-                        tokens.extend(self.first(c, syntax));
-                    }
-                }
+                spans.extend(self.drain(self.ty));
+                spans.extend(self.first(c, syntax, text));
             }
             (Ty::Str(q), _) => {
-                let control = self.buffer.ends_with('\\');
-                self.buffer.push(c);
-                if c == q && !control {
-                    tokens.extend(self.drain(Ty::Unknown));
+                let delimiter = self.quote.and_then(|i| syntax.delimiters().nth(i));
+                if delimiter.is_some_and(|d| d.heredoc) {
+                    if c == '\n' && self.heredoc_closed(text) {
+                        spans.extend(self.drain(Ty::Unknown));
+                        spans.extend(self.first(c, syntax, text));
+                    } else {
+                        self.push(c);
+                    }
+                    return;
+                }
+                let body = self.end - self.start >= self.marker && !self.escaped;
+                let rest = &text[self.end..];
+                if body && let Some(d) = delimiter {
+                    if d.doubled_at(rest) {
+                        self.push(c);
+                        self.escaped = true;
+                        return;
+                    }
+                    if let Some((open, code, close)) = d.interpolation_at(rest) {
+                        self.interpolations.push(Interpolating {
+                            q,
+                            quote: self.quote,
+                            repeat: self.repeat,
+                            end: self.end + open + code,
+                            close,
+                        });
+                        self.jump(text, open, Ty::Interpolation, spans);
+                        return;
+                    }
+                }
+                if body
+                    && delimiter.is_none_or(|d| d.escape.is_some())
+                    && let Some(len) = syntax.escape_at(rest)
+                {
+                    let (quote, repeat) = (self.quote, self.repeat);
+                    self.jump(text, len, Ty::Escape, spans);
+                    self.ty = Ty::Str(q);
+                    self.quote = quote;
+                    self.repeat = repeat;
+                    return;
+                }
+                self.push(c);
+                let len = self.end - self.start;
+                if len <= self.marker {
+                    // Opening delimiter
+                } else if self.escaped {
+                    self.escaped = false;
+                    self.marker = len;
+                } else if Some(c) == delimiter.map_or(Some('\\'), |d| d.escape) {
+                    self.escaped = true;
+                } else if delimiter.map_or(c == q, |d| {
+                    d.closes(&self.buffer(text)[self.marker..], self.repeat)
+                }) {
+                    spans.extend(self.drain(Ty::Unknown));
                 }
             }
             (Ty::Whitespace(_) | Ty::Unknown, _) => {
-                tokens.extend(self.first(c, syntax));
+                spans.extend(self.first(c, syntax, text));
             }
             // Keyword, Type, Special
             (_reserved, Ty::Literal | Ty::Numeric(_)) => {
-                self.buffer.push(c);
-                self.ty = if syntax.is_keyword(&self.buffer) {
+                self.push(c);
+                let buffer = self.buffer(text);
+                self.ty = if syntax.is_keyword(buffer) {
                     Ty::Keyword
-                } else if syntax.is_type(&self.buffer) {
+                } else if syntax.is_type(buffer) {
                     Ty::Type
-                } else if syntax.is_special(&self.buffer) {
+                } else if syntax.is_special(buffer) {
                     Ty::Special
                 } else {
                     Ty::Literal
@@ -244,17 +491,280 @@ impl Token {
             }
             (reserved, _) => {
                 self.ty = reserved;
-                tokens.extend(self.drain(self.ty));
-                tokens.extend(self.first(c, syntax));
+                spans.extend(self.drain(self.ty));
+                spans.extend(self.first(c, syntax, text));
+            }
+        }
+    }
+}
+
+/// Lexer iterator over spans of the source text, see [`Syntax::spans`].
+pub struct Spans<'a> {
+    syntax: &'a Syntax,
+    text: &'a str,
+    chars: Chars<'a>,
+    lexer: Lexer,
+    spans: VecDeque<Span>,
+}
+
+impl Iterator for Spans<'_> {
+    type Item = Span;
+
+    fn next(&mut self) -> Option<Span> {
+        loop {
+            if let Some(span) = self.spans.pop_front() {
+                return Some(span);
+            }
+            match self.chars.next() {
+                Some(c) => self
+                    .lexer
+                    .automata(c, self.syntax, self.text, &mut self.spans),
+                None => return self.lexer.drain(TokenType::Unknown),
+            }
+        }
+    }
+}
+
+impl Syntax {
+    /// Lexer without allocations: types with byte and char ranges of tokens in `text`.
+    pub fn spans<'a>(&'a self, text: &'a str) -> Spans<'a> {
+        Spans {
+            syntax: self,
+            text,
+            chars: text.chars(),
+            lexer: Lexer::default(),
+            spans: VecDeque::new(),
+        }
+    }
+
+    /// Spans with contents of injection regions and marked strings
+    /// replaced by spans of their own syntaxes.
+    pub fn inject(&self, text: &str, spans: impl IntoIterator<Item = Span>) -> Vec<Span> {
+        let mut spans = spans.into_iter().peekable();
+        if self.injections.is_empty() {
+            return spans.collect();
+        }
+        let mut injected = Vec::new();
+        let mut marked = None;
+        while let Some(mut span) = spans.next() {
+            let injection = match span.ty {
+                TokenType::Comment(_) => {
+                    marked = self.marked_by(span.slice(text));
+                    None
+                }
+                TokenType::Whitespace(_) => None,
+                TokenType::Str(_) => self.injection_at(span.slice(text), marked.take()),
+                _ => {
+                    marked = None;
+                    None
+                }
+            };
+            let Some(injection) = injection else {
+                injected.push(span);
+                continue;
+            };
+            // Escapes split strings into several spans.
+            let mut escape = false;
+            while let Some(next) = spans.next_if(|s| {
+                s.bytes.start == span.bytes.end
+                    && (s.ty == TokenType::Escape || escape && matches!(s.ty, TokenType::Str(_)))
+            }) {
+                escape = next.ty == TokenType::Escape;
+                span.bytes.end = next.bytes.end;
+                span.chars.end = next.chars.end;
+            }
+            let string = span.slice(text);
+            let body = self.string_body(string);
+            let code = &string[body.clone()];
+            let start = span.bytes.start + body.start;
+            let start_char = span.chars.start + string[..body.start].chars().count();
+            let end_char = start_char + code.chars().count();
+            injected.extend((body.start > 0).then_some(Span {
+                ty: span.ty,
+                bytes: span.bytes.start..start,
+                chars: span.chars.start..start_char,
+            }));
+            let syntax = &injection.syntax;
+            injected.extend(
+                syntax
+                    .inject(code, syntax.spans(code))
+                    .into_iter()
+                    .map(|mut s| {
+                        s.shift(start as isize, start_char as isize);
+                        s
+                    }),
+            );
+            injected.extend((body.end < string.len()).then_some(Span {
+                ty: span.ty,
+                bytes: start + code.len()..span.bytes.end,
+                chars: end_char..span.chars.end,
+            }));
+        }
+        injected
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// Lexer state snapshot at the start of a line, outside of tokens lexed ahead.
+struct LineStart {
+    /// Number of spans emitted before the line start.
+    span: usize,
+    /// Pending token: open multiline comment, open string or punctuation.
+    lexer: Lexer,
+}
+
+#[derive(Default, Debug, Clone)]
+/// Line-resumable lexer.
+///
+/// Keeps spans of the last lexed text with a lexer snapshot for every line start.
+/// After an edit lexing restarts from the first changed line and stops
+/// as soon as the lexer state matches the old one again.
+///
+/// Only lexing is incremental: [`Syntax::inject`], rainbow bracket depths and the
+/// [`LayoutJob`](egui::text::LayoutJob) of [`highlight`] still go over the whole text.
+pub struct Highlighter {
+    syntax: Option<Syntax>,
+    text: String,
+    spans: Vec<Span>,
+    lines: Vec<LineStart>,
+}
+
+impl Highlighter {
+    /// Spans of the last lexed text.
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// Relex only lines changed since the last call.
+    pub fn update(&mut self, syntax: &Syntax, text: &str) -> &[Span] {
+        if self.syntax.as_ref() != Some(syntax) {
+            *self = Highlighter {
+                syntax: Some(syntax.clone()),
+                ..Default::default()
+            };
+        }
+        if self.lines.is_empty() {
+            self.lines.push(LineStart::default());
+            self.relex(syntax, text, 0, 0);
+        } else if self.text != text {
+            let prefix = common_prefix(&self.text, text);
+            let suffix = common_suffix(&self.text[prefix..], &text[prefix..]);
+            // Lookahead from the char before a line start may have seen the edit.
+            let line = self
+                .lines
+                .partition_point(|l| l.lexer.end < prefix)
+                .saturating_sub(1);
+            self.relex(syntax, text, line, text.len() - suffix);
+        }
+        self.text.replace_range(.., text);
+        &self.spans
+    }
+
+    /// Lex `text` from the start of `line` until the lexer state after `changed_end`
+    /// matches the old state at the same line, then reuse the old spans.
+    fn relex(&mut self, syntax: &Syntax, text: &str, line: usize, changed_end: usize) {
+        let LineStart {
+            span: start_span,
+            mut lexer,
+        } = self.lines[line].clone();
+        let delta = text.len() as isize - self.text.len() as isize;
+        let mut spans = vec![];
+        let mut lines = vec![];
+
+        let mut end = lexer.end;
+        for c in text[lexer.end..].chars() {
+            lexer.automata(c, syntax, text, &mut spans);
+            end += c.len_utf8();
+            // Only resume at line starts outside of tokens lexed ahead.
+            if c != '\n' || lexer.skip > 0 || lexer.end != end {
+                continue;
+            }
+            let span = start_span + spans.len();
+            if lexer.end > changed_end
+                && let Ok(old) = self
+                    .lines
+                    .binary_search_by_key(&lexer.end.wrapping_add_signed(-delta), |l| l.lexer.end)
+                && self.lines[old].lexer.resumes(&self.text, &lexer, text)
+            {
+                let old_span = self.lines[old].span;
+                let span_delta = span as isize - old_span as isize;
+                let char_delta = lexer.end_char as isize - self.lines[old].lexer.end_char as isize;
+                for s in &mut self.spans[old_span..] {
+                    s.shift(delta, char_delta);
+                }
+                for l in &mut self.lines[old..] {
+                    l.span = l.span.wrapping_add_signed(span_delta);
+                    l.lexer.shift(delta, char_delta);
+                }
+                self.spans.splice(start_span..old_span, spans);
+                self.lines.splice(line + 1..old, lines);
+                return;
             }
+            lines.push(LineStart {
+                span,
+                lexer: lexer.clone(),
+            });
         }
-        tokens
+        spans.extend(lexer.drain(TokenType::Unknown));
+        self.spans.truncate(start_span);
+        self.spans.extend(spans);
+        self.lines.truncate(line + 1);
+        self.lines.extend(lines);
     }
 }
 
+/// Length in bytes of the common prefix, on a char boundary.
+pub(crate) fn common_prefix(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, ca), cb)| ca != cb)
+        .map_or(a.len().min(b.len()), |((i, _), _)| i)
+}
+
+/// Length in bytes of the common suffix, on a char boundary.
+pub(crate) fn common_suffix(a: &str, b: &str) -> usize {
+    a.chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(ca, cb)| ca == cb)
+        .map(|(c, _)| c.len_utf8())
+        .sum()
+}
+
+#[cfg(feature = "egui")]
+use super::brackets::{bracket_depths, brackets_in};
 #[cfg(feature = "egui")]
 use egui::text::LayoutJob;
 
+#[cfg(feature = "egui")]
+/// Layout spans of `text`, collecting char ranges of hyperlinks.
+fn layout<T: Editor>(
+    editor: &T,
+    text: &str,
+    spans: impl IntoIterator<Item = Span>,
+) -> (LayoutJob, Links) {
+    let mut job = LayoutJob::default();
+    let mut links = Links::new();
+    let spans: Vec<Span> = spans.into_iter().collect();
+    let mut depths = match editor.rainbow_brackets() {
+        true => bracket_depths(brackets_in(text, spans.iter().cloned())),
+        false => Vec::new(),
+    }
+    .into_iter()
+    .peekable();
+    for span in spans {
+        if span.ty == TokenType::Hyperlink {
+            links.push(span.chars.clone());
+        }
+        while depths.next_if(|(i, _)| *i < span.chars.start).is_some() {}
+        match depths.next_if(|(i, _)| *i == span.chars.start && span.chars.len() == 1) {
+            Some((_, depth)) => editor.append_bracket(&mut job, text, &span, depth),
+            None => editor.append_span(&mut job, text, &span),
+        }
+    }
+    (job, links)
+}
+
 #[cfg(feature = "egui")]
 impl<T: Editor> egui::cache::ComputerMut<(&T, &str, &Syntax), (LayoutJob, Links)> for Token {
     fn compute(&mut self, (cache, text, syntax): (&T, &str, &Syntax)) -> (LayoutJob, Links) {
@@ -263,7 +773,21 @@ impl<T: Editor> egui::cache::ComputerMut<(&T, &str, &Syntax), (LayoutJob, Links)
 }
 
 #[cfg(feature = "egui")]
-pub type HighlightCache = egui::cache::FrameCache<(LayoutJob, Links), Token>;
+#[derive(Default)]
+/// One [`Highlighter`] per [`Editor::id`], so editors shown together don't relex each other's text.
+pub struct Highlighters(std::collections::HashMap<String, Highlighter>);
+
+#[cfg(feature = "egui")]
+impl<T: Editor> egui::cache::ComputerMut<(&T, &str, &Syntax), (LayoutJob, Links)> for Highlighters {
+    fn compute(&mut self, (cache, text, syntax): (&T, &str, &Syntax)) -> (LayoutJob, Links) {
+        let highlighter = self.0.entry(cache.id().to_string()).or_default();
+        let spans = highlighter.update(syntax, text).iter().cloned();
+        layout(cache, text, syntax.inject(text, spans))
+    }
+}
+
+#[cfg(feature = "egui")]
+pub type HighlightCache = egui::cache::FrameCache<(LayoutJob, Links), Highlighters>;
 
 #[cfg(feature = "egui")]
 pub fn highlight<T: Editor>(
@@ -279,3 +803,53 @@ pub fn highlight<T: Editor>(
             .to_owned()
     })
 }
+
+#[cfg(feature = "egui")]
+/// Split sections of `job` at sorted non-overlapping byte `ranges`
+/// and `restyle` the parts inside them, with the index of their range.
+pub fn restyle_ranges(
+    job: &mut LayoutJob,
+    ranges: &[Range<usize>],
+    restyle: impl Fn(usize, &mut egui::TextFormat),
+) {
+    use egui::text::LayoutSection;
+
+    if ranges.is_empty() {
+        return;
+    }
+    let mut sections = Vec::with_capacity(job.sections.len());
+    for section in job.sections.drain(..) {
+        let (first, end) = (section.byte_range.start.0, section.byte_range.end.0);
+        let mut start = first;
+        let mut push = |range: Range<usize>, index: Option<usize>| {
+            let mut format = section.format.clone();
+            if let Some(index) = index {
+                restyle(index, &mut format);
+            }
+            sections.push(LayoutSection {
+                leading_space: if range.start == first {
+                    section.leading_space
+                } else {
+                    0.0
+                },
+                byte_range: range.start.into()..range.end.into(),
+                format,
+            });
+        };
+        let overlapping = ranges.partition_point(|r| r.end <= start);
+        for (index, range) in ranges.iter().enumerate().skip(overlapping) {
+            if range.start >= end {
+                break;
+            }
+            if range.start > start {
+                push(start..range.start, None);
+            }
+            push(start.max(range.start)..end.min(range.end), Some(index));
+            start = end.min(range.end);
+        }
+        if start < end {
+            push(start..end, None);
+        }
+    }
+    job.sections = sections;
+}
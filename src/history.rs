@@ -0,0 +1,233 @@
+use super::highlighting::{common_prefix, common_suffix};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// Source of an edit, deciding how it is grouped into undo steps.
+pub enum EditKind {
+    /// Typed chars, new lines and deletions.
+    Typing,
+    /// Completion inserted by [`Completer`](crate::Completer).
+    Completion,
+    Paste,
+    #[default]
+    Other,
+}
+
+#[cfg(feature = "egui")]
+impl EditKind {
+    /// Kind of the edit made by input `events` of a frame.
+    pub fn from_events(events: &[egui::Event]) -> Self {
+        use egui::{Event, Key};
+
+        events
+            .iter()
+            .fold(None, |kind, event| match event {
+                Event::Paste(_) => Some(EditKind::Paste),
+                Event::Text(_)
+                | Event::Key {
+                    key: Key::Backspace | Key::Delete | Key::Enter,
+                    pressed: true,
+                    ..
+                } => kind.or(Some(EditKind::Typing)),
+                _ => kind,
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// Grouping of edits into undo steps.
+pub struct Grouping {
+    /// Typing makes one step per word, otherwise one per edit.
+    pub words: bool,
+    /// A completion joins the step of the word it completes.
+    pub completions: bool,
+    /// A paste makes its own step, otherwise it joins typing.
+    pub pastes: bool,
+}
+
+impl Default for Grouping {
+    fn default() -> Self {
+        Grouping {
+            words: true,
+            completions: true,
+            pastes: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// Replacement of `removed` text by `inserted` one at char index `at`.
+pub struct Change {
+    pub at: usize,
+    pub removed: String,
+    pub inserted: String,
+}
+
+impl Change {
+    /// The changed middle of `old` and `new` texts, if they differ.
+    pub fn between(old: &str, new: &str) -> Option<Change> {
+        if old == new {
+            return None;
+        }
+        let prefix = common_prefix(old, new);
+        let suffix = common_suffix(&old[prefix..], &new[prefix..]);
+        Some(Change {
+            at: old[..prefix].chars().count(),
+            removed: old[prefix..old.len() - suffix].to_string(),
+            inserted: new[prefix..new.len() - suffix].to_string(),
+        })
+    }
+
+    /// Replace `removed` chars at `at` in `text` by `inserted` ones.
+    fn apply(text: &mut String, at: usize, removed: &str, inserted: &str) {
+        let byte = |chars: usize| {
+            text.char_indices()
+                .nth(chars)
+                .map_or(text.len(), |(i, _)| i)
+        };
+        let start = byte(at);
+        text.replace_range(start..start + removed.len(), inserted);
+    }
+
+    /// Does `self` continue `last` change, typed or deleted right after it.
+    fn continues(&self, last: &Change) -> bool {
+        let inserted = |c: &Change| c.removed.is_empty() && !c.inserted.is_empty();
+        let deleted = |c: &Change| c.inserted.is_empty() && !c.removed.is_empty();
+        if inserted(self) && inserted(last) {
+            self.at == last.at + last.inserted.chars().count()
+        } else if deleted(self) && deleted(last) {
+            self.at == last.at || self.at + self.removed.chars().count() == last.at
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// Changes undone and redone together.
+pub struct Step {
+    pub kind: EditKind,
+    pub changes: Vec<Change>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// Undo and redo stacks of a text, grouping its changes into steps.
+pub struct History {
+    pub grouping: Grouping,
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    /// Can the last step take more changes.
+    open: bool,
+    /// The text after the last change, to find the next one.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    text: Option<String>,
+}
+
+impl History {
+    pub fn new(grouping: Grouping) -> Self {
+        History {
+            grouping,
+            ..Default::default()
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn undo_steps(&self) -> &[Step] {
+        &self.undo
+    }
+
+    pub fn redo_steps(&self) -> &[Step] {
+        &self.redo
+    }
+
+    /// Record the change of `text` since the last call, made by an edit of `kind`.
+    /// The first call only remembers the text.
+    pub fn update(&mut self, text: &str, kind: EditKind) {
+        if self.text.as_deref() == Some(text) {
+            return;
+        }
+        if let Some(change) = self
+            .text
+            .as_deref()
+            .and_then(|old| Change::between(old, text))
+        {
+            self.record(change, kind);
+        }
+        self.text = Some(text.to_string());
+    }
+
+    /// Push `change` onto the undo stack, joining the last step if grouping allows.
+    pub fn record(&mut self, change: Change, kind: EditKind) {
+        self.redo.clear();
+        let kind = match kind {
+            EditKind::Paste if !self.grouping.pastes => EditKind::Typing,
+            kind => kind,
+        };
+        let joins = self.open
+            && self.undo.last().is_some_and(|step| {
+                let last = step.changes.last();
+                let word_ended = last.is_some_and(|l| {
+                    l.inserted
+                        .ends_with(|c: char| !c.is_alphanumeric() && c != '_')
+                        && change
+                            .inserted
+                            .starts_with(|c: char| c.is_alphanumeric() || c == '_')
+                });
+                step.kind == EditKind::Typing
+                    && last.is_some_and(|l| change.continues(l))
+                    && match kind {
+                        EditKind::Typing => self.grouping.words && !word_ended,
+                        EditKind::Completion => self.grouping.completions,
+                        _ => false,
+                    }
+            });
+        match self.undo.last_mut() {
+            Some(step) if joins => step.changes.push(change),
+            _ => self.undo.push(Step {
+                kind,
+                changes: vec![change],
+            }),
+        }
+        self.open = kind == EditKind::Typing;
+    }
+
+    /// Undo the last step in `text`, returning the char index of the caret after it.
+    pub fn undo(&mut self, text: &mut String) -> Option<usize> {
+        let step = self.undo.pop()?;
+        let mut caret = 0;
+        for change in step.changes.iter().rev() {
+            Change::apply(text, change.at, &change.inserted, &change.removed);
+            caret = change.at + change.removed.chars().count();
+        }
+        self.redo.push(step);
+        self.open = false;
+        self.text = Some(text.clone());
+        Some(caret)
+    }
+
+    /// Redo the last undone step in `text`, returning the char index of the caret after it.
+    pub fn redo(&mut self, text: &mut String) -> Option<usize> {
+        let step = self.redo.pop()?;
+        let mut caret = 0;
+        for change in &step.changes {
+            Change::apply(text, change.at, &change.removed, &change.inserted);
+            caret = change.at + change.inserted.chars().count();
+        }
+        self.undo.push(step);
+        self.open = false;
+        self.text = Some(text.clone());
+        Some(caret)
+    }
+}
@@ -1,5 +1,6 @@
 use crate::highlighting::Links;
 use egui::{Pos2, Rect, text_edit::TextEditOutput};
+use std::ops::Range;
 
 pub const SPACE_HOLDER: &str = "␣";
 
@@ -7,11 +8,9 @@ pub fn handle_links(text_edit: &TextEditOutput, links: &Links) {
     if !text_edit.response.contains_pointer() {
         return;
     }
-    let galley = &text_edit.galley;
-    let top_left = text_edit.galley_pos.to_vec2();
     let ctx = &text_edit.response.ctx;
 
-    let chars = galley.chars().collect::<Vec<char>>();
+    let chars = text_edit.galley.chars().collect::<Vec<char>>();
     links.iter().for_each(|link_range| {
         let link_start = link_range.start;
         let link_end = link_range.end;
@@ -19,15 +18,7 @@ pub fn handle_links(text_edit: &TextEditOutput, links: &Links) {
             .get(link_start..link_end)
             .map(|c| c.iter().collect::<String>())
         {
-            let cursors = (link_start..=link_end)
-                .map(|index| {
-                    galley.pos_from_cursor(egui::text::CCursor {
-                        index: index.into(),
-                        prefer_next_row: false,
-                    })
-                })
-                .collect::<Vec<Rect>>();
-            let rects = join_cursor_rects(&cursors, top_left);
+            let rects = char_rects(text_edit, link_start..link_end);
             for rect in rects {
                 if ctx.pointer_hover_pos().is_some_and(|p| rect.contains(p)) {
                     ctx.set_cursor_icon(egui::CursorIcon::PointingHand);
@@ -61,6 +52,19 @@ pub fn handle_links(text_edit: &TextEditOutput, links: &Links) {
     });
 }
 
+/// Screen rects of rows covered by the char `range` of the edited text.
+pub fn char_rects(text_edit: &TextEditOutput, range: Range<usize>) -> Vec<Rect> {
+    let cursors = (range.start..=range.end)
+        .map(|index| {
+            text_edit.galley.pos_from_cursor(egui::text::CCursor {
+                index: index.into(),
+                prefer_next_row: false,
+            })
+        })
+        .collect::<Vec<Rect>>();
+    join_cursor_rects(&cursors, text_edit.galley_pos.to_vec2())
+}
+
 fn join_cursor_rects(cursors: &[Rect], top_left: egui::Vec2) -> Vec<Rect> {
     let mut rects = Vec::<Rect>::new();
     if cursors.is_empty() {
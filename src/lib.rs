@@ -67,33 +67,73 @@
 //! }
 //! ```
 
+pub mod brackets;
 #[cfg(feature = "egui")]
 mod completer;
+#[cfg(feature = "egui")]
+pub mod cursors;
+pub mod diagnostics;
+pub mod folding;
+#[cfg(feature = "egui")]
+mod gutter;
 pub mod highlighting;
+pub mod history;
 #[cfg(feature = "egui")]
 mod hyperlinks;
+#[cfg(feature = "egui")]
+pub mod lines;
+pub mod search;
 mod syntax;
 #[cfg(test)]
 mod tests;
 mod themes;
+pub use brackets::matching_bracket;
 #[cfg(feature = "egui")]
-use egui::Stroke;
+use cursors::MultiCursor;
+pub use diagnostics::{Diagnostic, Severity};
+#[cfg(feature = "egui")]
+use diagnostics::{diagnostic_lines, line_severities};
 #[cfg(feature = "egui")]
 use egui::text::LayoutJob;
 #[cfg(feature = "egui")]
 use egui::widgets::text_edit::TextEditOutput;
-pub use highlighting::Token;
 #[cfg(feature = "egui")]
-use highlighting::highlight;
+use egui::{Color32, Stroke};
+pub use folding::fold_regions;
+#[cfg(feature = "egui")]
+use folding::{FoldCache, hidden_lines, hide_ranges, line_bytes};
+#[cfg(feature = "egui")]
+pub use gutter::GutterIcon;
+pub use highlighting::{Span, Token};
+#[cfg(feature = "egui")]
+use highlighting::{highlight, restyle_ranges};
+pub use history::{EditKind, Grouping, History};
 #[cfg(feature = "egui")]
 pub use hyperlinks::SPACE_HOLDER;
 #[cfg(feature = "egui")]
-use hyperlinks::handle_links;
+use hyperlinks::{char_rects, handle_links};
+#[cfg(feature = "egui")]
+pub use lines::{Indent, LineOp};
+#[cfg(feature = "egui")]
+use search::FindBar;
+pub use search::Search;
+#[cfg(any(feature = "egui", feature = "editor"))]
+use std::collections::BTreeSet;
 #[cfg(feature = "editor")]
 use std::hash::{Hash, Hasher};
-pub use syntax::{Patch, Syntax, TokenType};
+#[cfg(feature = "egui")]
+use std::ops::Range;
+#[cfg(any(feature = "toml", feature = "json"))]
+pub use syntax::loader::LoadError;
+pub use syntax::pattern::{Pattern, PatternError};
+pub use syntax::registry::{SyntaxEntry, SyntaxRegistry};
+pub use syntax::{
+    Escape, Injection, Interpolation, Numbers, Opening, Patch, StringDelimiter, Syntax, TokenType,
+};
 pub use themes::ColorTheme;
 pub use themes::DEFAULT_THEMES;
+#[cfg(feature = "egui")]
+use themes::ERROR_COLOR;
 
 #[cfg(feature = "egui")]
 pub use crate::completer::Completer;
@@ -101,6 +141,26 @@ pub use crate::completer::Completer;
 #[cfg(feature = "egui")]
 pub trait Editor: Hash {
     fn append(&self, job: &mut LayoutJob, token: &Token);
+
+    /// Identifies the incremental highlighter kept for this editor by [`highlighting::highlight`].
+    fn id(&self) -> &str {
+        ""
+    }
+
+    /// Append a span of `text` without copying it into a [`Token`] first.
+    fn append_span(&self, job: &mut LayoutJob, text: &str, span: &Span) {
+        self.append(job, &Token::new(span.ty, span.slice(text)));
+    }
+
+    /// Color brackets by nesting depth with [`Editor::append_bracket`].
+    fn rainbow_brackets(&self) -> bool {
+        false
+    }
+
+    /// Append a bracket at nesting `depth`, `None` if unbalanced.
+    fn append_bracket(&self, job: &mut LayoutJob, text: &str, span: &Span, _depth: Option<usize>) {
+        self.append_span(job, text, span);
+    }
 }
 
 #[cfg(feature = "editor")]
@@ -121,6 +181,18 @@ pub struct CodeEditor {
     desired_width: f32,
     wrap: bool,
     hint_text: Option<String>,
+    rainbow_brackets: bool,
+    folding: bool,
+    diagnostics: Option<Vec<Diagnostic>>,
+    multi_cursor: bool,
+    undo_grouping: Grouping,
+    edit_kind: Option<EditKind>,
+    line_marking: bool,
+    marked_lines: Option<BTreeSet<usize>>,
+    #[cfg(feature = "egui")]
+    gutter_icons: Option<Vec<GutterIcon>>,
+    #[cfg(feature = "egui")]
+    smart_indent: Option<Indent>,
 }
 
 #[cfg(feature = "editor")]
@@ -129,6 +201,7 @@ impl Hash for CodeEditor {
         self.theme.hash(state);
         #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
         (self.fontsize as u32).hash(state);
+        self.rainbow_brackets.hash(state);
     }
 }
 
@@ -149,6 +222,18 @@ impl Default for CodeEditor {
             desired_width: f32::INFINITY,
             wrap: false,
             hint_text: None,
+            rainbow_brackets: false,
+            folding: false,
+            diagnostics: None,
+            multi_cursor: false,
+            undo_grouping: Grouping::default(),
+            edit_kind: None,
+            line_marking: false,
+            marked_lines: None,
+            #[cfg(feature = "egui")]
+            gutter_icons: None,
+            #[cfg(feature = "egui")]
+            smart_indent: None,
         }
     }
 }
@@ -233,6 +318,143 @@ impl CodeEditor {
     pub fn with_wrap(self, wrap: bool) -> Self {
         CodeEditor { wrap, ..self }
     }
+
+    /// Color brackets by nesting depth, unbalanced ones with the error color.
+    ///
+    /// **Default: false**
+    pub fn with_rainbow_brackets(self, rainbow_brackets: bool) -> Self {
+        CodeEditor {
+            rainbow_brackets,
+            ..self
+        }
+    }
+
+    /// Fold regions of brackets, indentation and fold markers by clicking markers
+    /// in the lines numbering. Folded lines are hidden, the text stays unchanged.
+    ///
+    /// **Default: false**
+    pub fn with_folding(self, folding: bool) -> Self {
+        CodeEditor { folding, ..self }
+    }
+
+    /// Show diagnostics of an external checker: squiggles under their ranges,
+    /// icons in the lines numbering and messages on hover. Squiggles are painted over the
+    /// text, not put in the [`LayoutJob`] of [`highlighting::highlight`];
+    /// [`diagnostics::underline_diagnostics`] adds them to a job for other widgets.
+    ///
+    /// **Default: none**
+    pub fn with_diagnostics(self, diagnostics: &[Diagnostic]) -> Self {
+        CodeEditor {
+            diagnostics: Some(diagnostics.to_vec()),
+            ..self
+        }
+    }
+
+    /// Edit with several cursors: Ctrl+click adds one, Ctrl+D selects the next occurrence
+    /// and Alt+drag makes a box selection, see [`MultiCursor`](cursors::MultiCursor).
+    ///
+    /// **Default: false**
+    pub fn with_multi_cursor(self, multi_cursor: bool) -> Self {
+        CodeEditor {
+            multi_cursor,
+            ..self
+        }
+    }
+
+    /// Group edits into undo steps: per word, per completion and per paste.
+    /// Ctrl+Z undoes a step, Ctrl+Shift+Z or Ctrl+Y redoes it.
+    ///
+    /// **Default: all grouped**
+    pub fn with_undo_grouping(self, undo_grouping: Grouping) -> Self {
+        CodeEditor {
+            undo_grouping,
+            ..self
+        }
+    }
+
+    #[cfg(feature = "egui")]
+    /// Undo history of the editor with this id. It is kept in egui temp memory, which is not
+    /// persisted, so with `serde` feature save it yourself to restore it in the next run:
+    ///
+    /// ```
+    /// # #[cfg(feature = "json")]
+    /// # {
+    /// use egui_code_editor::{CodeEditor, History};
+    ///
+    /// let ctx = egui::Context::default();
+    /// let editor = CodeEditor::default().id_source("code");
+    /// let saved = serde_json::to_string(&editor.load_history(&ctx).unwrap_or_default()).unwrap();
+    ///
+    /// let history: History = serde_json::from_str(&saved).unwrap();
+    /// editor.store_history(&ctx, history);
+    /// # }
+    /// ```
+    pub fn load_history(&self, ctx: &egui::Context) -> Option<History> {
+        ctx.data(|d| d.get_temp(egui::Id::new(format!("{}_history", self.id))))
+    }
+
+    #[cfg(feature = "egui")]
+    /// Replace undo history of the editor with this id, like one saved from [`CodeEditor::load_history`].
+    pub fn store_history(&self, ctx: &egui::Context, history: History) {
+        ctx.data_mut(|d| d.insert_temp(egui::Id::new(format!("{}_history", self.id)), history));
+    }
+
+    #[cfg(feature = "egui")]
+    /// Marked line indices of the editor with this id, as kept between frames.
+    pub fn load_marked_lines(&self, ctx: &egui::Context) -> BTreeSet<usize> {
+        ctx.data(|d| d.get_temp(egui::Id::new(format!("{}_marked", self.id))))
+            .unwrap_or_default()
+    }
+
+    /// Mark lines, like breakpoints or bookmarks, by clicking the lines numbering.
+    ///
+    /// **Default: false**
+    pub fn with_line_marking(self, line_marking: bool) -> Self {
+        CodeEditor {
+            line_marking,
+            ..self
+        }
+    }
+
+    /// Replace marked line indices. Otherwise the editor keeps them in egui temp memory
+    /// under its id, so a `CodeEditor` built anew every frame finds them again.
+    /// Read them with [`CodeEditor::load_marked_lines`], or [`CodeEditor::marked_lines`]
+    /// on the same value after [`CodeEditor::show`].
+    pub fn with_marked_lines(self, lines: impl IntoIterator<Item = usize>) -> Self {
+        CodeEditor {
+            marked_lines: Some(lines.into_iter().collect()),
+            ..self
+        }
+    }
+
+    /// Marked line indices after [`CodeEditor::show`] of this value, `None` before it.
+    pub fn marked_lines(&self) -> Option<&BTreeSet<usize>> {
+        self.marked_lines.as_ref()
+    }
+
+    #[cfg(feature = "egui")]
+    /// Show custom icons in the lines numbering, over line markers and diagnostics.
+    ///
+    /// **Default: none**
+    pub fn with_gutter_icons(self, gutter_icons: &[GutterIcon]) -> Self {
+        CodeEditor {
+            gutter_icons: Some(gutter_icons.to_vec()),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "egui")]
+    /// Indent with Tab and new lines by `indent`, following [`Syntax::indent_after`]
+    /// and [`Syntax::dedent_before`] rules. Tab and Shift+Tab indent and outdent selected lines.
+    /// Overrides [`Completer::with_auto_indent`].
+    ///
+    /// **Default: none, Tab inserts `'\t'`**
+    pub fn with_smart_indent(self, indent: Indent) -> Self {
+        CodeEditor {
+            smart_indent: Some(indent),
+            ..self
+        }
+    }
     // Use custom syntax for highlighting
     //
     // **Default: Rust**
@@ -298,7 +520,38 @@ impl CodeEditor {
     }
 
     #[cfg(feature = "egui")]
-    fn numlines_show(&self, ui: &mut egui::Ui, text: &str) {
+    /// Icon of every line in the lines numbering:
+    /// custom gutter icon, then line marker, then the most severe diagnostic.
+    fn gutter_icons(&self, text: &str, marked: &BTreeSet<usize>) -> Vec<Option<(char, Color32)>> {
+        let mut icons = match &self.diagnostics {
+            Some(diagnostics) => line_severities(text, diagnostics)
+                .into_iter()
+                .map(|s| s.map(|s| (s.icon(), self.theme.severity_color(s))))
+                .collect(),
+            None => vec![None; text.matches('\n').count() + 1],
+        };
+        for line in marked {
+            if let Some(icon) = icons.get_mut(*line) {
+                *icon = Some(('●', ERROR_COLOR));
+            }
+        }
+        for icon in self.gutter_icons.iter().flatten().rev() {
+            if let Some(line) = icons.get_mut(icon.line) {
+                *line = Some((icon.icon, icon.color));
+            }
+        }
+        icons
+    }
+
+    #[cfg(feature = "egui")]
+    fn numlines_show(
+        &self,
+        ui: &mut egui::Ui,
+        text: &str,
+        regions: &[Range<usize>],
+        folded: &mut BTreeSet<usize>,
+        marked: &mut BTreeSet<usize>,
+    ) {
         use egui::TextBuffer;
 
         let total = if text.ends_with('\n') || text.is_empty() {
@@ -311,15 +564,34 @@ impl CodeEditor {
             .to_string()
             .len()
             .max(!self.numlines_only_natural as usize * self.numlines_shift.to_string().len());
+        let with_icons = self.diagnostics.is_some()
+            || self.line_marking
+            || self.gutter_icons.is_some()
+            || !marked.is_empty();
+        let icons = self.gutter_icons(text, marked);
+        let mut regions_iter = regions.iter().map(|r| r.start).peekable();
         let mut counter = (1..=total)
             .map(|i| {
                 let num = i + self.numlines_shift;
+                let marker = match self.folding {
+                    false => "",
+                    true => match regions_iter.next_if_eq(&(i as usize - 1)) {
+                        Some(line) if folded.contains(&line) => " ▸",
+                        Some(_) => " ▾",
+                        None => "  ",
+                    },
+                };
+                let icon = match icons.get(i as usize - 1) {
+                    _ if !with_icons => String::new(),
+                    Some(Some((icon, _))) => format!("{icon} "),
+                    _ => "  ".to_string(),
+                };
                 if num <= 0 && self.numlines_only_natural {
-                    String::new()
+                    format!("{icon}{}", marker.trim_start())
                 } else {
                     let label = num.to_string();
                     format!(
-                        "{}{label}",
+                        "{icon}{}{label}{marker}",
                         " ".repeat(max_indent.saturating_sub(label.len()))
                     )
                 }
@@ -328,32 +600,338 @@ impl CodeEditor {
             .join("\n");
 
         #[allow(clippy::cast_precision_loss)]
-        let width = max_indent as f32
+        let width = (max_indent + 2 * self.folding as usize + 2 * with_icons as usize) as f32
             * self.fontsize
             * 0.5
             * !(total + self.numlines_shift <= 0 && self.numlines_only_natural) as u8 as f32;
 
+        let hidden = hidden_lines(regions, folded);
         let mut layouter = |ui: &egui::Ui, text_buffer: &dyn TextBuffer, _wrap_width: f32| {
-            let layout_job = egui::text::LayoutJob::single_section(
-                text_buffer.as_str().to_string(),
-                egui::TextFormat::simple(
-                    egui::FontId::monospace(self.fontsize),
-                    self.theme.type_color(TokenType::Comment(true)),
-                ),
+            let format = egui::TextFormat::simple(
+                egui::FontId::monospace(self.fontsize),
+                self.theme.type_color(TokenType::Comment(true)),
             );
+            let mut layout_job = egui::text::LayoutJob::default();
+            for (line, label) in text_buffer.as_str().split_inclusive('\n').enumerate() {
+                let icon = label.chars().next().map_or(0, char::len_utf8);
+                match icons.get(line) {
+                    Some(Some((_, color))) => {
+                        let icon_format = egui::TextFormat {
+                            color: *color,
+                            ..format.clone()
+                        };
+                        layout_job.append(&label[..icon], 0.0, icon_format);
+                        layout_job.append(&label[icon..], 0.0, format.clone());
+                    }
+                    _ => layout_job.append(label, 0.0, format.clone()),
+                }
+            }
+            hide_ranges(&mut layout_job, &line_bytes(text_buffer.as_str(), &hidden));
             ui.fonts_mut(|f| f.layout_job(layout_job))
         };
 
-        ui.add(
-            egui::TextEdit::multiline(&mut counter)
-                .id_source(format!("{}_numlines", self.id))
-                .font(egui::TextStyle::Monospace)
-                .interactive(false)
-                .frame(egui::Frame::NONE)
-                .desired_rows(self.rows)
-                .desired_width(width)
-                .layouter(&mut layouter),
-        );
+        let output = egui::TextEdit::multiline(&mut counter)
+            .id_source(format!("{}_numlines", self.id))
+            .font(egui::TextStyle::Monospace)
+            .interactive(false)
+            .frame(egui::Frame::NONE)
+            .desired_rows(self.rows)
+            .desired_width(width)
+            .layouter(&mut layouter)
+            .show(ui);
+
+        if regions.is_empty() && !with_icons {
+            return;
+        }
+        let id = egui::Id::new(format!("{}_gutter", self.id));
+        let response = ui.interact(output.response.response.rect, id, egui::Sense::click());
+        let Some(pos) = response.hover_pos() else {
+            return;
+        };
+        let cursor: usize = output
+            .galley
+            .cursor_from_pos(pos - output.galley_pos)
+            .index
+            .into();
+        let line = counter.chars().take(cursor).filter(|c| *c == '\n').count();
+        let line_start = counter
+            .chars()
+            .take(cursor)
+            .collect::<String>()
+            .rfind('\n')
+            .map_or(0, |n| counter[..n].chars().count() + 1);
+        let label_len = counter.lines().nth(line).map_or(0, |l| l.chars().count());
+        let on_fold_marker = self.folding && cursor - line_start + 2 >= label_len;
+        if on_fold_marker && regions.iter().any(|r| r.start == line) {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+            if response.clicked() && !folded.remove(&line) {
+                folded.insert(line);
+            }
+        } else if self.line_marking && line < icons.len() {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+            if response.clicked() && !marked.remove(&line) {
+                marked.insert(line);
+            }
+        }
+        if let Some(diagnostics) = &self.diagnostics {
+            let lines = diagnostic_lines(text, diagnostics);
+            if lines.contains(&line) {
+                response.on_hover_ui_at_pointer(|ui| {
+                    for (_, diagnostic) in
+                        lines.iter().zip(diagnostics).filter(|(l, _)| **l == line)
+                    {
+                        ui.label(diagnostic.to_string());
+                    }
+                });
+            }
+        }
+    }
+
+    #[cfg(feature = "egui")]
+    /// Squiggles under diagnostics and their messages on hover.
+    fn diagnostics_show(&self, ui: &egui::Ui, output: &TextEditOutput) {
+        let Some(diagnostics) = &self.diagnostics else {
+            return;
+        };
+        let painter = ui.painter().with_clip_rect(output.text_clip_rect);
+        let hover = output.response.response.hover_pos();
+        let mut hovered = Vec::new();
+        for diagnostic in diagnostics {
+            let stroke = Stroke::new(
+                self.fontsize * 0.08,
+                self.theme.severity_color(diagnostic.severity),
+            );
+            let wave = self.fontsize * 0.15;
+            for rect in char_rects(output, diagnostic.marked()) {
+                if rect.height() < 1.0 {
+                    continue;
+                }
+                if hover.is_some_and(|p| rect.contains(p)) {
+                    hovered.push(diagnostic);
+                }
+                let points = (0..=(rect.width() / wave) as usize)
+                    .map(|n| {
+                        let y = rect.max.y - wave * (n % 2) as f32;
+                        egui::pos2(rect.min.x + wave * n as f32, y)
+                    })
+                    .collect();
+                painter.add(egui::Shape::line(points, stroke));
+            }
+        }
+        if !hovered.is_empty() {
+            output
+                .response
+                .response
+                .clone()
+                .on_hover_ui_at_pointer(|ui| {
+                    for diagnostic in hovered {
+                        ui.label(diagnostic.to_string());
+                    }
+                });
+        }
+    }
+
+    #[cfg(feature = "egui")]
+    /// Outline the bracket at the cursor and its matching pair.
+    fn brackets_show(&self, ui: &egui::Ui, output: &TextEditOutput, syntax: &Syntax) {
+        use egui::text::CCursor;
+
+        let Some(range) = output.cursor_range.filter(|r| r.is_empty()) else {
+            return;
+        };
+        let text = output.galley.text();
+        let cursor: usize = range.primary.index.into();
+        let pair = ui.memory_mut(|m| {
+            let pairs = m
+                .caches
+                .cache::<brackets::BracketCache>()
+                .get((text, syntax));
+            [Some(cursor), cursor.checked_sub(1)]
+                .into_iter()
+                .flatten()
+                .find_map(|i| {
+                    let n = pairs
+                        .binary_search_by_key(&i, |(bracket, _)| *bracket)
+                        .ok()?;
+                    Some(pairs[n])
+                })
+        });
+        let Some((bracket, matching)) = pair else {
+            return;
+        };
+        let painter = ui.painter().with_clip_rect(output.text_clip_rect);
+        let stroke = Stroke::new(self.fontsize * 0.1, self.theme.bracket_match());
+        for i in [bracket, matching] {
+            let min = output.galley.pos_from_cursor(CCursor::new(i)).min;
+            let max = output.galley.pos_from_cursor(CCursor::new(i + 1)).max;
+            let rect = egui::Rect::from_min_max(min, max).translate(output.galley_pos.to_vec2());
+            painter.rect_stroke(rect, 0.0, stroke, egui::StrokeKind::Inside);
+        }
+    }
+
+    #[cfg(feature = "egui")]
+    /// Undo or redo a step of `history` by Ctrl+Z, Ctrl+Shift+Z or Ctrl+Y.
+    fn undo_show(
+        &self,
+        ctx: &egui::Context,
+        id: egui::Id,
+        history: &mut History,
+        text: &mut dyn egui::TextBuffer,
+    ) {
+        use egui::text::{CCursor, CCursorRange};
+        use egui::{Key, Modifiers};
+
+        let (redo, undo) = ctx.input_mut(|i| {
+            let redo = i.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z)
+                || i.consume_key(Modifiers::COMMAND, Key::Y);
+            (redo, i.consume_key(Modifiers::COMMAND, Key::Z))
+        });
+        if !undo && !redo {
+            return;
+        }
+        let mut buffer = text.as_str().to_string();
+        let caret = match redo {
+            true => history.redo(&mut buffer),
+            false => history.undo(&mut buffer),
+        };
+        if let Some(caret) = caret {
+            text.replace_with(&buffer);
+            let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+            state
+                .cursor
+                .set_char_range(Some(CCursorRange::one(CCursor::new(caret))));
+            state.store(ctx, id);
+        }
+    }
+
+    #[cfg(feature = "egui")]
+    /// Apply a [`LineOp`] by its shortcut to the lines of the selection,
+    /// toggle line comments by Ctrl+/ and block comments by Ctrl+Shift+/.
+    fn line_ops_input(
+        &self,
+        ctx: &egui::Context,
+        id: egui::Id,
+        text: &mut dyn egui::TextBuffer,
+        syntax: &Syntax,
+        multi_cursor: &mut MultiCursor,
+    ) {
+        use egui::text::{CCursor, CCursorRange};
+        use egui::{Key, KeyboardShortcut, Modifiers};
+        use lines::{apply_at_cursors, consume_exact, toggle_block_comment, toggle_line_comment};
+
+        let (op, block_comment, line_comment) = ctx.input_mut(|i| {
+            let command_shift = Modifiers::COMMAND | Modifiers::SHIFT;
+            let mut consume =
+                |modifiers, key| consume_exact(i, &KeyboardShortcut::new(modifiers, key));
+            let block_comment =
+                consume(command_shift, Key::Slash) || consume(command_shift, Key::Questionmark);
+            let line_comment = consume(Modifiers::COMMAND, Key::Slash);
+            let op = LineOp::ALL
+                .into_iter()
+                .find(|op| consume_exact(i, &op.shortcut()));
+            (op, block_comment, line_comment)
+        });
+        if op.is_none() && !block_comment && !line_comment {
+            return;
+        }
+        let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+        let primary = state.cursor.char_range().map_or(0..0, |r| {
+            let range = r.as_sorted_char_range();
+            range.start.0..range.end.0
+        });
+        let mut cursors: Vec<_> = multi_cursor
+            .extra
+            .iter()
+            .map(|c| c.start.min(c.end)..c.start.max(c.end))
+            .chain([primary])
+            .collect();
+        let keep = op.is_some_and(|op| op.keeps_cursors());
+        cursors = apply_at_cursors(text, &cursors, keep, |text, selection| match op {
+            Some(op) => op.apply(text, selection),
+            None if block_comment => toggle_block_comment(text, selection, syntax),
+            None => toggle_line_comment(text, selection, syntax),
+        });
+        let selection = cursors.pop().unwrap_or_default();
+        cursors.retain(|c| *c != selection);
+        cursors.dedup();
+        multi_cursor.extra = cursors;
+        multi_cursor.edited(text.as_str());
+        state.cursor.set_char_range(Some(CCursorRange::two(
+            CCursor::new(selection.start),
+            CCursor::new(selection.end),
+        )));
+        state.store(ctx, id);
+    }
+
+    #[cfg(feature = "egui")]
+    /// Indent with Tab and Shift+Tab, break lines with Enter and dedent typed closing tokens.
+    fn indent_input(
+        &self,
+        ctx: &egui::Context,
+        id: egui::Id,
+        text: &mut dyn egui::TextBuffer,
+        syntax: &Syntax,
+        indent: Indent,
+    ) {
+        use egui::text::{CCursor, CCursorRange, CharIndex};
+        use egui::{Event, Key, Modifiers};
+        use lines::{dedent_typed, indent_lines, insert_newline, outdent_lines};
+
+        let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+        let Some(mut selection) = state.cursor.char_range().map(|r| {
+            let range = r.as_sorted_char_range();
+            range.start.0..range.end.0
+        }) else {
+            return;
+        };
+        let (outdent, tab, enter) = ctx.input_mut(|i| {
+            let outdent = i.consume_key(Modifiers::SHIFT, Key::Tab);
+            let tab = !outdent && i.consume_key(Modifiers::NONE, Key::Tab);
+            let enter = !outdent && !tab && i.consume_key(Modifiers::NONE, Key::Enter);
+            (outdent, tab, enter)
+        });
+        let changed = outdent || tab || enter;
+        if outdent {
+            selection = outdent_lines(text, selection, indent);
+        } else if tab
+            && text
+                .char_range(CharIndex(selection.start)..CharIndex(selection.end))
+                .contains('\n')
+        {
+            selection = indent_lines(text, selection, indent);
+        } else if tab {
+            let inserted = text.insert_text(&indent.unit(), CharIndex(selection.end));
+            text.delete_char_range(CharIndex(selection.start)..CharIndex(selection.end));
+            let caret = selection.start + inserted;
+            selection = caret..caret;
+        } else if enter {
+            selection = insert_newline(text, selection, syntax, indent);
+        }
+        let typed = ctx.input_mut(|i| {
+            let (mut first, mut typed) = (!changed, false);
+            i.events.retain(|event| match event {
+                Event::Text(t) if std::mem::take(&mut first) => {
+                    match dedent_typed(text, selection.clone(), t, syntax, indent) {
+                        Some(caret) => {
+                            selection = caret;
+                            typed = true;
+                            false
+                        }
+                        None => true,
+                    }
+                }
+                _ => true,
+            });
+            typed
+        });
+        if !changed && !typed {
+            return;
+        }
+        state.cursor.set_char_range(Some(CCursorRange::two(
+            CCursor::new(selection.start),
+            CCursor::new(selection.end),
+        )));
+        state.store(ctx, id);
     }
 
     #[cfg(feature = "egui")]
@@ -365,7 +943,11 @@ impl CodeEditor {
         syntax: &Syntax,
         completer: &mut Completer,
     ) -> TextEditOutput {
+        if self.smart_indent.is_some() {
+            completer.clear_indent();
+        }
         completer.handle_input(ui.ctx());
+        self.edit_kind = completer.take_edit();
         let mut editor_output = self.show(ui, text, syntax);
         completer.show(syntax, &self.theme, self.fontsize, &mut editor_output);
         editor_output
@@ -381,6 +963,56 @@ impl CodeEditor {
     ) -> TextEditOutput {
         use egui::TextBuffer;
 
+        let folded_id = egui::Id::new(format!("{}_folded", self.id));
+        let regions = match self.folding {
+            true => ui.memory_mut(|mem| {
+                mem.caches
+                    .cache::<FoldCache>()
+                    .get((text.as_str(), syntax))
+                    .to_owned()
+            }),
+            false => Vec::new(),
+        };
+        let mut folded: BTreeSet<usize> = ui.data(|d| d.get_temp(folded_id).unwrap_or_default());
+        folded.retain(|line| regions.iter().any(|r| r.start == *line));
+        let marked_id = egui::Id::new(format!("{}_marked", self.id));
+        let mut marked: BTreeSet<usize> = match self.marked_lines.take() {
+            Some(marked) => marked,
+            None => ui.data(|d| d.get_temp(marked_id).unwrap_or_default()),
+        };
+        let text_edit_id = egui::Id::new(format!("{}_text_edit", self.id));
+        let history_id = egui::Id::new(format!("{}_history", self.id));
+        let mut history: History = ui
+            .data_mut(|d| d.remove_temp(history_id))
+            .unwrap_or_default();
+        history.grouping = self.undo_grouping;
+        let edit_kind = self
+            .edit_kind
+            .take()
+            .unwrap_or_else(|| ui.input(|i| EditKind::from_events(&i.events)));
+        let focused = ui
+            .data(|d| d.get_temp::<egui::Id>(text_edit_id))
+            .filter(|id| ui.memory(|m| m.has_focus(*id)));
+        let cursors_id = egui::Id::new(format!("{}_cursors", self.id));
+        let mut cursors: MultiCursor = ui.data(|d| d.get_temp(cursors_id).unwrap_or_default());
+        cursors.sync(text.as_str());
+        if let Some(id) = focused {
+            self.undo_show(ui.ctx(), id, &mut history, text);
+            cursors.sync(text.as_str());
+            self.line_ops_input(ui.ctx(), id, text, syntax, &mut cursors);
+        }
+        let find_id = egui::Id::new(format!("{}_find", self.id));
+        let mut find: FindBar = ui.data(|d| d.get_temp(find_id).unwrap_or_default());
+        find.handle_input(ui.ctx(), find_id, text.as_str());
+        if self.multi_cursor {
+            cursors.handle_input(ui.ctx(), text);
+        }
+        if let (Some(id), Some(indent)) = (focused, self.smart_indent)
+            && cursors.extra.is_empty()
+        {
+            self.indent_input(ui.ctx(), id, text, syntax, indent);
+        }
+
         let mut text_edit_output: Option<TextEditOutput> = None;
         let mut code_editor = |ui: &mut egui::Ui| {
             let frame = egui::Frame::new().fill(self.theme.bg());
@@ -388,8 +1020,9 @@ impl CodeEditor {
                 ui.horizontal_top(|h| {
                     self.theme.modify_style(h, self.fontsize);
                     if self.numlines {
-                        self.numlines_show(h, text.as_str());
+                        self.numlines_show(h, text.as_str(), &regions, &mut folded, &mut marked);
                     }
+                    let hidden = hidden_lines(&regions, &folded);
                     egui::ScrollArea::horizontal()
                         .id_salt(format!("{}_inner_scroll", self.id))
                         .show(h, |ui| {
@@ -402,6 +1035,15 @@ impl CodeEditor {
                                     let (mut layout_job, links) =
                                         highlight(ui.ctx(), self, text_str, syntax);
                                     links_ranges = links;
+                                    let matches = find.matches(ui.ctx(), text_str);
+                                    let current = find.current(ui.ctx(), text_str, &matches);
+                                    restyle_ranges(&mut layout_job, &matches, |i, format| {
+                                        format.background = match Some(i) == current {
+                                            true => self.theme.selection(),
+                                            false => self.theme.selection().gamma_multiply(0.5),
+                                        };
+                                    });
+                                    hide_ranges(&mut layout_job, &line_bytes(text_str, &hidden));
 
                                     if !self.numlines && self.wrap {
                                         layout_job.wrap =
@@ -424,6 +1066,11 @@ impl CodeEditor {
                             if self.clickable_links {
                                 handle_links(&output, &links_ranges);
                             }
+                            self.brackets_show(ui, &output, syntax);
+                            self.diagnostics_show(ui, &output);
+                            if self.multi_cursor {
+                                cursors.show(ui, &output, &self.theme, self.fontsize);
+                            }
 
                             text_edit_output = Some(output);
                         });
@@ -438,8 +1085,17 @@ impl CodeEditor {
         } else {
             code_editor(ui);
         }
-
-        text_edit_output.expect("TextEditOutput should exist at this point")
+        let output = text_edit_output.expect("TextEditOutput should exist at this point");
+        find.show(ui, find_id, text, &output);
+        history.update(text.as_str(), edit_kind);
+        ui.data_mut(|d| d.insert_temp(history_id, history));
+        ui.data_mut(|d| d.insert_temp(text_edit_id, output.response.response.id));
+        ui.data_mut(|d| d.insert_temp(find_id, find));
+        ui.data_mut(|d| d.insert_temp(cursors_id, cursors));
+        ui.data_mut(|d| d.insert_temp(folded_id, folded));
+        ui.data_mut(|d| d.insert_temp(marked_id, marked.clone()));
+        self.marked_lines = Some(marked);
+        output
     }
 }
 
@@ -451,6 +1107,26 @@ impl Editor for CodeEditor {
             job.append(token.buffer(), 0.0, self.format_token(token.ty()));
         }
     }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn append_span(&self, job: &mut LayoutJob, text: &str, span: &Span) {
+        if !span.bytes.is_empty() {
+            job.append(span.slice(text), 0.0, self.format_token(span.ty));
+        }
+    }
+
+    fn rainbow_brackets(&self) -> bool {
+        self.rainbow_brackets
+    }
+
+    fn append_bracket(&self, job: &mut LayoutJob, text: &str, span: &Span, depth: Option<usize>) {
+        let mut format = self.format_token(span.ty);
+        format.color = self.theme.bracket_color(depth);
+        job.append(span.slice(text), 0.0, format);
+    }
 }
 
 #[cfg(feature = "egui")]
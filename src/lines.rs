@@ -0,0 +1,636 @@
+use crate::Syntax;
+use egui::{Key, KeyboardShortcut, Modifiers, TextBuffer, text::CharIndex};
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Operation on whole lines touched by the selection.
+pub enum LineOp {
+    Duplicate,
+    MoveUp,
+    MoveDown,
+    Delete,
+    Join,
+}
+
+impl LineOp {
+    /// All operations.
+    pub const ALL: [LineOp; 5] = [
+        LineOp::Duplicate,
+        LineOp::Delete,
+        LineOp::MoveUp,
+        LineOp::MoveDown,
+        LineOp::Join,
+    ];
+
+    pub fn shortcut(&self) -> KeyboardShortcut {
+        let command_shift = Modifiers::COMMAND | Modifiers::SHIFT;
+        match self {
+            LineOp::Duplicate => KeyboardShortcut::new(command_shift, Key::D),
+            LineOp::MoveUp => KeyboardShortcut::new(Modifiers::ALT, Key::ArrowUp),
+            LineOp::MoveDown => KeyboardShortcut::new(Modifiers::ALT, Key::ArrowDown),
+            LineOp::Delete => KeyboardShortcut::new(command_shift, Key::K),
+            LineOp::Join => KeyboardShortcut::new(Modifiers::COMMAND, Key::J),
+        }
+    }
+
+    /// Apply to lines of `text` touched by the sorted char `selection`, returning the new one.
+    pub fn apply(&self, text: &mut dyn TextBuffer, selection: Range<usize>) -> Range<usize> {
+        match self {
+            LineOp::Duplicate => duplicate_lines(text, selection),
+            LineOp::MoveUp => move_lines(text, selection, true),
+            LineOp::MoveDown => move_lines(text, selection, false),
+            LineOp::Delete => delete_lines(text, selection),
+            LineOp::Join => join_lines(text, selection),
+        }
+    }
+
+    /// Whether cursors acting as one selection are all kept, see [`apply_at_cursors`].
+    pub fn keeps_cursors(&self) -> bool {
+        matches!(self, LineOp::Duplicate | LineOp::MoveUp | LineOp::MoveDown)
+    }
+}
+
+/// Remove a press of `shortcut` with exactly its modifiers from `input`,
+/// so that Ctrl+Shift+J doesn't count as Ctrl+J.
+pub fn consume_exact(input: &mut egui::InputState, shortcut: &KeyboardShortcut) -> bool {
+    let count = input.events.len();
+    input.events.retain(|event| {
+        !matches!(event, egui::Event::Key { key, pressed: true, modifiers, .. }
+            if *key == shortcut.logical_key && modifiers.matches_exact(shortcut.modifiers))
+    });
+    input.events.len() < count
+}
+
+/// Apply `op` to every sorted char selection in `cursors`, returning their new selections
+/// in the same order. Cursors on the same or adjacent lines act as one selection:
+/// they are all shifted along with it if `keep`, otherwise they merge into its result.
+pub fn apply_at_cursors(
+    text: &mut dyn TextBuffer,
+    cursors: &[Range<usize>],
+    keep: bool,
+    mut op: impl FnMut(&mut dyn TextBuffer, Range<usize>) -> Range<usize>,
+) -> Vec<Range<usize>> {
+    let starts = line_starts(text.as_str());
+    let mut order: Vec<usize> = (0..cursors.len()).collect();
+    order.sort_by_key(|&i| cursors[i].start);
+    let mut groups: Vec<(Range<usize>, Vec<usize>)> = Vec::new();
+    for i in order {
+        let lines = touched_lines(&starts, &cursors[i]);
+        match groups.last_mut() {
+            Some((group, members)) if lines.start <= group.end => {
+                group.end = group.end.max(lines.end);
+                members.push(i);
+            }
+            _ => groups.push((lines, vec![i])),
+        }
+    }
+    let mut selections = cursors.to_vec();
+    let mut below: Vec<usize> = Vec::new();
+    // Bottom up, so that edits don't move the cursors still to be processed.
+    for (lines, members) in groups.into_iter().rev() {
+        let start = members.iter().map(|&i| cursors[i].start).min();
+        let end = members.iter().map(|&i| cursors[i].end).max();
+        let mut joined = start.unwrap_or_default()..end.unwrap_or_default();
+        if members.len() > 1 {
+            joined.end = joined
+                .end
+                .max(lines_chars(text.as_str(), &starts, &lines).end);
+        }
+        let len = text.as_str().chars().count();
+        let selection = op(text, joined.clone());
+        let delta = text.as_str().chars().count() as isize - len as isize;
+        for &i in &below {
+            selections[i] = shift(selections[i].clone(), delta);
+        }
+        for &i in &members {
+            selections[i] = match keep {
+                true => shift(
+                    cursors[i].clone(),
+                    selection.start as isize - joined.start as isize,
+                ),
+                false => selection.clone(),
+            };
+        }
+        below.extend(members);
+    }
+    selections
+}
+
+/// Char indices of line starts in `text`.
+fn line_starts(text: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(
+            text.chars()
+                .enumerate()
+                .filter_map(|(i, c)| (c == '\n').then_some(i + 1)),
+        )
+        .collect()
+}
+
+/// Lines touched by `selection`, excluding the last one if the selection ends at its start.
+fn touched_lines(starts: &[usize], selection: &Range<usize>) -> Range<usize> {
+    let line = |i: usize| starts.partition_point(|&s| s <= i) - 1;
+    let first = line(selection.start);
+    let last = line(selection.end);
+    let last = match selection.end > selection.start && starts[last] == selection.end {
+        true => last - 1,
+        false => last,
+    };
+    first..last.max(first) + 1
+}
+
+/// Char range of `lines` without the line break after them.
+fn lines_chars(text: &str, starts: &[usize], lines: &Range<usize>) -> Range<usize> {
+    let end = starts
+        .get(lines.end)
+        .map_or(text.chars().count(), |s| s - 1);
+    starts[lines.start]..end
+}
+
+fn replace(text: &mut dyn TextBuffer, range: Range<usize>, with: &str) {
+    text.delete_char_range(CharIndex(range.start)..CharIndex(range.end));
+    text.insert_text(with, CharIndex(range.start));
+}
+
+fn shift(selection: Range<usize>, delta: isize) -> Range<usize> {
+    selection.start.saturating_add_signed(delta)..selection.end.saturating_add_signed(delta)
+}
+
+/// Copy touched lines below them, selecting the copy.
+pub fn duplicate_lines(text: &mut dyn TextBuffer, selection: Range<usize>) -> Range<usize> {
+    let starts = line_starts(text.as_str());
+    let block = lines_chars(text.as_str(), &starts, &touched_lines(&starts, &selection));
+    let copy = format!(
+        "\n{}",
+        text.char_range(CharIndex(block.start)..CharIndex(block.end))
+    );
+    text.insert_text(&copy, CharIndex(block.end));
+    shift(selection, block.len() as isize + 1)
+}
+
+/// Swap touched lines with the line above or below them.
+pub fn move_lines(text: &mut dyn TextBuffer, selection: Range<usize>, up: bool) -> Range<usize> {
+    let starts = line_starts(text.as_str());
+    let lines = touched_lines(&starts, &selection);
+    let other = match up {
+        true if lines.start > 0 => lines.start - 1..lines.start,
+        false if lines.end < starts.len() => lines.end..lines.end + 1,
+        _ => return selection,
+    };
+    let block = lines_chars(text.as_str(), &starts, &lines);
+    let other = lines_chars(text.as_str(), &starts, &other);
+    let slice =
+        |range: &Range<usize>| text.char_range(CharIndex(range.start)..CharIndex(range.end));
+    let (block_text, other_text) = (slice(&block).to_string(), slice(&other).to_string());
+    match up {
+        true => {
+            replace(
+                text,
+                other.start..block.end,
+                &format!("{block_text}\n{other_text}"),
+            );
+            shift(selection, -(other.len() as isize + 1))
+        }
+        false => {
+            replace(
+                text,
+                block.start..other.end,
+                &format!("{other_text}\n{block_text}"),
+            );
+            shift(selection, other.len() as isize + 1)
+        }
+    }
+}
+
+/// Remove touched lines, placing the caret at the start of the next one.
+pub fn delete_lines(text: &mut dyn TextBuffer, selection: Range<usize>) -> Range<usize> {
+    let starts = line_starts(text.as_str());
+    let lines = touched_lines(&starts, &selection);
+    let block = lines_chars(text.as_str(), &starts, &lines);
+    let (removed, caret) = match (lines.end < starts.len(), lines.start > 0) {
+        (true, _) => (block.start..block.end + 1, block.start),
+        (false, true) => (block.start - 1..block.end, starts[lines.start - 1]),
+        (false, false) => (block.clone(), 0),
+    };
+    text.delete_char_range(CharIndex(removed.start)..CharIndex(removed.end));
+    caret..caret
+}
+
+/// Join touched lines, or the line with the next one, with single spaces.
+pub fn join_lines(text: &mut dyn TextBuffer, selection: Range<usize>) -> Range<usize> {
+    let starts = line_starts(text.as_str());
+    let mut lines = touched_lines(&starts, &selection);
+    if lines.len() == 1 {
+        lines.end = (lines.end + 1).min(starts.len());
+    }
+    let block = lines_chars(text.as_str(), &starts, &lines);
+    let block_text = text.char_range(CharIndex(block.start)..CharIndex(block.end));
+    let mut joined = String::new();
+    for (n, line) in block_text.split('\n').enumerate() {
+        let line = match n {
+            0 => line.trim_end(),
+            _ => line.trim(),
+        };
+        if n > 0 && !line.is_empty() && !joined.is_empty() {
+            joined.push(' ');
+        }
+        joined.push_str(line);
+    }
+    let first_len = block_text
+        .split('\n')
+        .next()
+        .map_or(0, |l| l.trim_end().chars().count());
+    let joined_len = joined.chars().count();
+    replace(text, block.clone(), &joined);
+    match selection.is_empty() {
+        true => block.start + first_len..block.start + first_len,
+        false => block.start..block.start + joined_len,
+    }
+}
+
+/// Char position `p` after replacing `removed` chars at `at` by `inserted` ones.
+/// A `caret` right at an insertion moves past it.
+fn map_position(p: usize, at: usize, removed: usize, inserted: usize, caret: bool) -> usize {
+    if p < at || p == at && !(removed == 0 && caret) {
+        p
+    } else if p < at + removed {
+        at
+    } else {
+        p + inserted - removed
+    }
+}
+
+/// Comment or uncomment touched lines with [`Syntax::comment`], at the smallest indentation
+/// of non-blank lines. Lines are uncommented if all of them start with a line comment marker.
+/// Falls back to a block comment without line comment markers.
+pub fn toggle_line_comment(
+    text: &mut dyn TextBuffer,
+    selection: Range<usize>,
+    syntax: &Syntax,
+) -> Range<usize> {
+    if syntax.comment().is_empty() {
+        return toggle_block_comment(text, selection, syntax);
+    }
+    let starts = line_starts(text.as_str());
+    let lines = touched_lines(&starts, &selection);
+    let block = lines_chars(text.as_str(), &starts, &lines);
+    let block_text = text
+        .char_range(CharIndex(block.start)..CharIndex(block.end))
+        .to_string();
+    let indent = |line: &str| line.chars().take_while(|c| c.is_whitespace()).count();
+    let marker = |line: &str| {
+        syntax
+            .comments()
+            .filter(|m| !m.is_empty() && line.trim_start().starts_with(m))
+            .max_by_key(|m| m.len())
+            .map(|m| m.chars().count())
+    };
+    let filled: Vec<&str> = block_text
+        .split('\n')
+        .filter(|l| !l.trim().is_empty())
+        .collect();
+    let uncomment = !filled.is_empty() && filled.iter().all(|l| marker(l).is_some());
+    let column = filled.iter().map(|l| indent(l)).min().unwrap_or_default();
+    let mut edits = Vec::new();
+    let mut line_start = block.start;
+    for line in block_text.split('\n') {
+        let len = line.chars().count();
+        if !line.trim().is_empty() {
+            let edit = match uncomment {
+                true => {
+                    let at = line_start + indent(line);
+                    let marker_len = marker(line).unwrap_or_default();
+                    let space = line.chars().nth(indent(line) + marker_len) == Some(' ');
+                    (at, marker_len + space as usize, String::new())
+                }
+                false => (line_start + column, 0, format!("{} ", syntax.comment())),
+            };
+            edits.push(edit);
+        }
+        line_start += len + 1;
+    }
+    apply_edits(text, selection, edits)
+}
+
+/// Replace chars at sorted non-overlapping `(at, removed, inserted)` edits,
+/// mapping `selection` through them.
+fn apply_edits(
+    text: &mut dyn TextBuffer,
+    selection: Range<usize>,
+    edits: Vec<(usize, usize, String)>,
+) -> Range<usize> {
+    let caret = selection.is_empty();
+    let (mut start, mut end) = (selection.start, selection.end);
+    for (at, removed, inserted) in edits.into_iter().rev() {
+        replace(text, at..at + removed, &inserted);
+        let inserted = inserted.chars().count();
+        start = map_position(start, at, removed, inserted, caret);
+        end = map_position(end, at, removed, inserted, true);
+    }
+    start..end
+}
+
+/// Wrap the selection, or the touched line without its indentation, in
+/// [`Syntax::comment_multiline`] delimiters, or unwrap it if it is already wrapped.
+/// Falls back to line comments without block comment delimiters.
+pub fn toggle_block_comment(
+    text: &mut dyn TextBuffer,
+    selection: Range<usize>,
+    syntax: &Syntax,
+) -> Range<usize> {
+    let [open, close] = &syntax.comment_multiline;
+    if open.is_empty() || close.is_empty() {
+        return match syntax.comment().is_empty() {
+            true => selection,
+            false => toggle_line_comment(text, selection, syntax),
+        };
+    }
+    let range = match selection.is_empty() {
+        true => {
+            let starts = line_starts(text.as_str());
+            lines_chars(text.as_str(), &starts, &touched_lines(&starts, &selection))
+        }
+        false => selection.clone(),
+    };
+    let chars: Vec<char> = text
+        .char_range(CharIndex(range.start)..CharIndex(range.end))
+        .chars()
+        .collect();
+    let leading = chars.iter().take_while(|c| c.is_whitespace()).count();
+    let trailing = chars[leading..]
+        .iter()
+        .rev()
+        .take_while(|c| c.is_whitespace())
+        .count();
+    let range = range.start + leading..range.end - trailing;
+    let inner: String = chars[leading..chars.len() - trailing].iter().collect();
+    let (open_len, close_len) = (open.chars().count(), close.chars().count());
+    let edits = match inner
+        .strip_prefix(open.as_ref())
+        .and_then(|i| i.strip_suffix(close.as_ref()))
+    {
+        Some(body) => {
+            let open_space = body.starts_with(' ') as usize;
+            let close_space = (body.len() > open_space && body.ends_with(' ')) as usize;
+            vec![
+                (range.start, open_len + open_space, String::new()),
+                (
+                    range.end - close_len - close_space,
+                    close_len + close_space,
+                    String::new(),
+                ),
+            ]
+        }
+        None if range.is_empty() => return selection,
+        None => vec![
+            (range.start, 0, format!("{open} ")),
+            (range.end, 0, format!(" {close}")),
+        ],
+    };
+    apply_edits(text, selection, edits)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// Indentation level inserted by Tab and new lines.
+pub enum Indent {
+    Tabs,
+    Spaces(usize),
+}
+
+impl Indent {
+    /// Whitespace of one level.
+    pub fn unit(&self) -> String {
+        match self {
+            Indent::Tabs => "\t".to_string(),
+            Indent::Spaces(n) => " ".repeat(*n),
+        }
+    }
+
+    /// Columns of one level, also the width of a tab.
+    fn width(&self) -> usize {
+        match self {
+            Indent::Tabs => 4,
+            Indent::Spaces(n) => (*n).max(1),
+        }
+    }
+
+    /// Columns of leading `whitespace`.
+    fn columns(&self, whitespace: &str) -> usize {
+        whitespace
+            .chars()
+            .map(|c| match c {
+                '\t' => self.width(),
+                _ => 1,
+            })
+            .sum()
+    }
+
+    /// Leading whitespace of `columns`.
+    fn whitespace(&self, columns: usize) -> String {
+        match self {
+            Indent::Tabs => format!(
+                "{}{}",
+                "\t".repeat(columns / self.width()),
+                " ".repeat(columns % self.width())
+            ),
+            Indent::Spaces(_) => " ".repeat(columns),
+        }
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Does `line` end with one of [`Syntax::indent_after`], words at a word boundary.
+fn opens(line: &str, syntax: &Syntax) -> bool {
+    let line = line.trim_end();
+    syntax.indent_after.iter().any(|token| {
+        !token.is_empty()
+            && line
+                .strip_suffix(token.as_ref())
+                .is_some_and(|rest| !(token.starts_with(is_word) && rest.ends_with(is_word)))
+    })
+}
+
+/// Is `typed`, a line start without indentation, one of [`Syntax::dedent_before`],
+/// words followed by one non-word char.
+fn closes(typed: &str, syntax: &Syntax) -> bool {
+    syntax
+        .dedent_before
+        .iter()
+        .any(|token| match token.starts_with(is_word) {
+            true => typed.strip_prefix(token.as_ref()).is_some_and(|rest| {
+                let mut chars = rest.chars();
+                chars.next().is_some_and(|c| !is_word(c)) && chars.next().is_none()
+            }),
+            false => !token.is_empty() && typed == token,
+        })
+}
+
+/// Edit reindenting the line of `caret` one level below the line above, if its start up to
+/// the caret followed by `next` closes a block.
+fn dedent_edit(
+    text: &str,
+    caret: usize,
+    next: &str,
+    syntax: &Syntax,
+    indent: Indent,
+) -> Option<(usize, usize, String)> {
+    let starts = line_starts(text);
+    let line = starts.partition_point(|&s| s <= caret) - 1;
+    let before: String = text
+        .chars()
+        .skip(starts[line])
+        .take(caret - starts[line])
+        .collect();
+    if !closes(&format!("{}{next}", before.trim_start()), syntax) {
+        return None;
+    }
+    let expected = text
+        .split('\n')
+        .take(line)
+        .filter(|l| !l.trim().is_empty())
+        .last()
+        .map_or(0, |above| {
+            indent.columns(leading_whitespace(above))
+                + opens(above, syntax) as usize * indent.width()
+        });
+    let current = leading_whitespace(&before);
+    let columns = expected.saturating_sub(indent.width());
+    (indent.columns(current) > columns).then(|| {
+        (
+            starts[line],
+            current.chars().count(),
+            indent.whitespace(columns),
+        )
+    })
+}
+
+/// Change in char count made by a `dedent` edit.
+fn delta(dedent: Option<&(usize, usize, String)>) -> isize {
+    dedent.map_or(0, |(_, removed, whitespace)| {
+        whitespace.chars().count() as isize - *removed as isize
+    })
+}
+
+/// Insert one level at the start of non-blank touched lines.
+pub fn indent_lines(
+    text: &mut dyn TextBuffer,
+    selection: Range<usize>,
+    indent: Indent,
+) -> Range<usize> {
+    let starts = line_starts(text.as_str());
+    let lines = touched_lines(&starts, &selection);
+    let edits = text
+        .as_str()
+        .split('\n')
+        .enumerate()
+        .filter(|(n, line)| lines.contains(n) && !line.trim().is_empty())
+        .map(|(n, _)| (starts[n], 0, indent.unit()))
+        .collect();
+    apply_edits(text, selection, edits)
+}
+
+/// Remove up to one level from the start of touched lines.
+pub fn outdent_lines(
+    text: &mut dyn TextBuffer,
+    selection: Range<usize>,
+    indent: Indent,
+) -> Range<usize> {
+    let starts = line_starts(text.as_str());
+    let lines = touched_lines(&starts, &selection);
+    let edits = text
+        .as_str()
+        .split('\n')
+        .enumerate()
+        .filter(|(n, _)| lines.contains(n))
+        .filter_map(|(n, line)| {
+            let mut columns = 0;
+            let removed = leading_whitespace(line)
+                .chars()
+                .take_while(|c| {
+                    let fits = columns < indent.width();
+                    columns += indent.columns(&c.to_string());
+                    fits
+                })
+                .count();
+            (removed > 0).then(|| (starts[n], removed, String::new()))
+        })
+        .collect();
+    apply_edits(text, selection, edits)
+}
+
+/// Replace the selection by a line break and the indentation of the line, one level more
+/// after [`Syntax::indent_after`]. A closing token after the caret moves to a line of its own,
+/// and one before it is dedented first.
+pub fn insert_newline(
+    text: &mut dyn TextBuffer,
+    selection: Range<usize>,
+    syntax: &Syntax,
+    indent: Indent,
+) -> Range<usize> {
+    let dedent = dedent_edit(text.as_str(), selection.start, "\n", syntax, indent);
+    let starts = line_starts(text.as_str());
+    let line = touched_lines(&starts, &(selection.start..selection.start)).start;
+    let line_end = lines_chars(text.as_str(), &starts, &(line..line + 1)).end;
+    let slice = |range: Range<usize>| -> String {
+        text.as_str()
+            .chars()
+            .skip(range.start)
+            .take(range.end.saturating_sub(range.start))
+            .collect()
+    };
+    let before = slice(starts[line]..selection.start);
+    let after = slice(selection.end..line_end);
+    let base = match &dedent {
+        Some((_, _, whitespace)) => whitespace.clone(),
+        None => leading_whitespace(&before).to_string(),
+    };
+    let mut inserted = format!("\n{base}");
+    let mut closing = String::new();
+    if opens(&before, syntax) {
+        inserted.push_str(&indent.unit());
+        if syntax
+            .dedent_before
+            .iter()
+            .any(|token| !token.starts_with(is_word) && after.trim().starts_with(token.as_ref()))
+        {
+            closing = format!("\n{base}");
+        }
+    }
+    let caret =
+        (selection.start + inserted.chars().count()).saturating_add_signed(delta(dedent.as_ref()));
+    inserted.push_str(&closing);
+    let edits = dedent
+        .into_iter()
+        .chain([(selection.start, selection.len(), inserted)])
+        .collect();
+    apply_edits(text, selection, edits);
+    caret..caret
+}
+
+/// Replace the selection by `typed` text and dedent its line, if it closes a block with
+/// [`Syntax::dedent_before`]. Returns `None` and leaves the text as is otherwise.
+pub fn dedent_typed(
+    text: &mut dyn TextBuffer,
+    selection: Range<usize>,
+    typed: &str,
+    syntax: &Syntax,
+    indent: Indent,
+) -> Option<Range<usize>> {
+    let dedent = dedent_edit(text.as_str(), selection.start, typed, syntax, indent)?;
+    let caret =
+        (selection.start + typed.chars().count()).saturating_add_signed(delta(Some(&dedent)));
+    let edits = vec![
+        dedent,
+        (selection.start, selection.len(), typed.to_string()),
+    ];
+    apply_edits(text, selection, edits);
+    Some(caret..caret)
+}
@@ -0,0 +1,362 @@
+//! Find and replace in plain text, without egui.
+
+use super::syntax::pattern::{Pattern, PatternError};
+use std::ops::Range;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// Search query with its matching modes.
+pub struct Search {
+    pub query: String,
+    pub case_insensitive: bool,
+    /// Match only whole words, not parts of longer ones.
+    pub whole_word: bool,
+    /// Treat the query as a [`Pattern`].
+    pub regex: bool,
+}
+
+impl Search {
+    pub fn new<S: Into<String>>(query: S) -> Self {
+        Search {
+            query: query.into(),
+            ..Default::default()
+        }
+    }
+    pub fn with_case_insensitive(self, case_insensitive: bool) -> Self {
+        Search {
+            case_insensitive,
+            ..self
+        }
+    }
+    pub fn with_whole_word(self, whole_word: bool) -> Self {
+        Search { whole_word, ..self }
+    }
+    pub fn with_regex(self, regex: bool) -> Self {
+        Search { regex, ..self }
+    }
+
+    /// Query compiled to a [`Pattern`], with special chars escaped unless it is a regex.
+    pub fn pattern(&self) -> Result<Pattern, PatternError> {
+        let source = match self.regex {
+            true => self.query.clone(),
+            false => self
+                .query
+                .chars()
+                .flat_map(|c| match c.is_alphanumeric() || c.is_whitespace() {
+                    true => vec![c],
+                    false => vec!['\\', c],
+                })
+                .collect(),
+        };
+        Ok(Pattern::new(source)?.with_case_insensitive(self.case_insensitive))
+    }
+
+    /// Byte ranges of non-overlapping matches in `text`, none for an empty query.
+    pub fn matches(&self, text: &str) -> Result<Vec<Range<usize>>, PatternError> {
+        if self.query.is_empty() {
+            return Ok(Vec::new());
+        }
+        let pattern = self.pattern()?;
+        let word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let mut matches = Vec::new();
+        let mut start = 0;
+        while start < text.len() {
+            let line_start = start == 0 || text[..start].ends_with('\n');
+            let found = pattern
+                .len_at(&text[start..], line_start)
+                .map(|len| start..start + len)
+                .filter(|m| {
+                    !self.whole_word
+                        || !word(text[..m.start].chars().next_back())
+                            && !word(text[m.end..].chars().next())
+                });
+            match found {
+                Some(m) => {
+                    start = m.end;
+                    matches.push(m);
+                }
+                None => start += text[start..].chars().next().map_or(1, char::len_utf8),
+            }
+        }
+        Ok(matches)
+    }
+
+    #[cfg(feature = "egui")]
+    /// [`Search::matches`] cached in `ctx` memory while `text` and the search stay the same.
+    pub fn cached_matches(
+        &self,
+        ctx: &egui::Context,
+        text: &str,
+    ) -> Result<Vec<Range<usize>>, PatternError> {
+        ctx.memory_mut(|m| m.caches.cache::<SearchCache>().get((text, self)).clone())
+    }
+
+    /// `text` with every match replaced by `replacement`.
+    pub fn replace_all(&self, text: &str, replacement: &str) -> Result<String, PatternError> {
+        let mut replaced = String::with_capacity(text.len());
+        let mut last = 0;
+        for m in self.matches(text)? {
+            replaced.push_str(&text[last..m.start]);
+            replaced.push_str(replacement);
+            last = m.end;
+        }
+        replaced.push_str(&text[last..]);
+        Ok(replaced)
+    }
+}
+
+#[cfg(feature = "egui")]
+#[derive(Default)]
+pub struct Searcher;
+
+#[cfg(feature = "egui")]
+impl egui::cache::ComputerMut<(&str, &Search), Result<Vec<Range<usize>>, PatternError>>
+    for Searcher
+{
+    fn compute(
+        &mut self,
+        (text, search): (&str, &Search),
+    ) -> Result<Vec<Range<usize>>, PatternError> {
+        search.matches(text)
+    }
+}
+
+#[cfg(feature = "egui")]
+pub type SearchCache = egui::cache::FrameCache<Result<Vec<Range<usize>>, PatternError>, Searcher>;
+
+/// Index of the first match starting at `offset` or after it,
+/// or of the last one ending before it going `backwards`, wrapping around.
+pub fn nearest_match(matches: &[Range<usize>], offset: usize, backwards: bool) -> Option<usize> {
+    if matches.is_empty() {
+        return None;
+    }
+    Some(match backwards {
+        false => Some(matches.partition_point(|m| m.start < offset))
+            .filter(|&i| i < matches.len())
+            .unwrap_or(0),
+        true => matches
+            .partition_point(|m| m.end <= offset)
+            .checked_sub(1)
+            .unwrap_or(matches.len() - 1),
+    })
+}
+
+#[cfg(feature = "egui")]
+#[derive(Clone, Debug, Default)]
+/// Find and replace bar under the editor, opened by Ctrl+F and Ctrl+H, closed by Escape.
+/// Enter goes to the next match, Shift+Enter to the previous one.
+pub struct FindBar {
+    pub search: Search,
+    pub replacement: String,
+    open: bool,
+    replace: bool,
+    focus: bool,
+    text_edit_id: Option<egui::Id>,
+}
+
+#[cfg(feature = "egui")]
+impl FindBar {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Byte ranges of matches in `text` while the bar is open.
+    pub fn matches(&self, ctx: &egui::Context, text: &str) -> Vec<Range<usize>> {
+        match self.open {
+            true => self.search.cached_matches(ctx, text).unwrap_or_default(),
+            false => Vec::new(),
+        }
+    }
+
+    /// Byte range selected in the text editor.
+    fn selection(&self, ctx: &egui::Context, text: &str) -> Option<Range<usize>> {
+        use egui::text_selection::text_cursor_state::byte_index_from_char_index;
+
+        let range = egui::TextEdit::load_state(ctx, self.text_edit_id?)?
+            .cursor
+            .char_range()?
+            .as_sorted_char_range();
+        Some(
+            byte_index_from_char_index(text, range.start).0
+                ..byte_index_from_char_index(text, range.end).0,
+        )
+    }
+
+    /// Index of the match selected in the text editor.
+    pub fn current(
+        &self,
+        ctx: &egui::Context,
+        text: &str,
+        matches: &[Range<usize>],
+    ) -> Option<usize> {
+        let selection = self.selection(ctx, text)?;
+        matches.iter().position(|m| *m == selection)
+    }
+
+    /// Should be called before the text editor, as Ctrl+H deletes a char in it.
+    pub fn handle_input(&mut self, ctx: &egui::Context, id: egui::Id, text: &str) {
+        use egui::{Key, Modifiers};
+
+        let focused = ctx.memory(|m| m.focused());
+        let editor_focused = focused.is_some_and(|f| Some(f) == self.text_edit_id);
+        let bar_focused =
+            focused.is_some_and(|f| f == id.with("query") || f == id.with("replacement"));
+        if !editor_focused && !bar_focused {
+            return;
+        }
+        let (find, replace) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::COMMAND, Key::F),
+                i.consume_key(Modifiers::COMMAND, Key::H),
+            )
+        });
+        if find || replace {
+            self.open = true;
+            self.replace = replace;
+            self.focus = true;
+            if let Some(selection) = self.selection(ctx, text).filter(|s| !s.is_empty())
+                && editor_focused
+                && !text[selection.clone()].contains('\n')
+            {
+                self.search.query = text[selection].to_string();
+            }
+        } else if bar_focused && ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Escape)) {
+            self.open = false;
+            if let Some(id) = self.text_edit_id {
+                ctx.memory_mut(|m| m.request_focus(id));
+            }
+        }
+    }
+
+    /// Should be called after the text editor, replaces matches in `text`.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        id: egui::Id,
+        text: &mut dyn egui::TextBuffer,
+        output: &egui::text_edit::TextEditOutput,
+    ) {
+        use egui::text::{CCursor, CCursorRange};
+        use egui::{Key, TextEdit};
+
+        self.text_edit_id = Some(output.response.response.id);
+        if !self.open {
+            return;
+        }
+        let matches = self.search.cached_matches(ui.ctx(), text.as_str());
+        let current = matches
+            .as_ref()
+            .ok()
+            .and_then(|m| self.current(ui.ctx(), text.as_str(), m));
+        let width = ui.text_style_height(&egui::TextStyle::Monospace) * 12.0;
+        let enter = |ui: &egui::Ui, field: &egui::Response| {
+            field.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter))
+        };
+        let mut navigate = None;
+        let (mut replace, mut replace_all) = (false, false);
+        ui.horizontal(|ui| {
+            let query = TextEdit::singleline(&mut self.search.query)
+                .id(id.with("query"))
+                .hint_text("Find")
+                .desired_width(width)
+                .show(ui)
+                .response
+                .response;
+            if std::mem::take(&mut self.focus) {
+                query.request_focus();
+            }
+            if enter(ui, &query) {
+                navigate = Some(ui.input(|i| i.modifiers.shift));
+                query.request_focus();
+            }
+            let mut match_case = !self.search.case_insensitive;
+            ui.toggle_value(&mut match_case, "Aa")
+                .on_hover_text("Match case");
+            self.search.case_insensitive = !match_case;
+            ui.toggle_value(&mut self.search.whole_word, "W")
+                .on_hover_text("Whole word");
+            ui.toggle_value(&mut self.search.regex, ".*")
+                .on_hover_text("Regular expression");
+            ui.label(match &matches {
+                Err(error) => error.to_string(),
+                Ok(matches) if matches.is_empty() => "No results".to_string(),
+                Ok(matches) => format!("{}/{}", current.map_or(0, |i| i + 1), matches.len()),
+            });
+            if ui.button("▲").on_hover_text("Previous match").clicked() {
+                navigate = Some(true);
+            }
+            if ui.button("▼").on_hover_text("Next match").clicked() {
+                navigate = Some(false);
+            }
+            ui.toggle_value(&mut self.replace, "Replace");
+            if ui.button("×").on_hover_text("Close").clicked() {
+                self.open = false;
+            }
+        });
+        if self.replace && self.open {
+            ui.horizontal(|ui| {
+                let field = TextEdit::singleline(&mut self.replacement)
+                    .id(id.with("replacement"))
+                    .hint_text("Replace")
+                    .desired_width(width)
+                    .show(ui)
+                    .response
+                    .response;
+                if enter(ui, &field) {
+                    replace = true;
+                    field.request_focus();
+                }
+                replace |= ui.button("Replace").clicked();
+                replace_all = ui.button("Replace all").clicked();
+            });
+        }
+
+        let Ok(mut matches) = matches else {
+            return;
+        };
+        let mut offset = None;
+        if replace_all && !matches.is_empty() {
+            if let Ok(replaced) = self.search.replace_all(text.as_str(), &self.replacement) {
+                text.replace_with(&replaced);
+            }
+            return;
+        }
+        if replace {
+            if let Some(m) = current.map(|i| matches[i].clone()) {
+                let start = text.char_index_from_byte_index(m.start.into());
+                let end = text.char_index_from_byte_index(m.end.into());
+                text.delete_char_range(start..end);
+                text.insert_text(&self.replacement, start);
+                offset = Some(m.start + self.replacement.len());
+                matches = self
+                    .search
+                    .cached_matches(ui.ctx(), text.as_str())
+                    .unwrap_or_default();
+            }
+            navigate = Some(false);
+        }
+        let Some(backwards) = navigate else {
+            return;
+        };
+        let selection = self.selection(ui.ctx(), text.as_str()).unwrap_or_default();
+        let offset = offset.unwrap_or(match backwards {
+            true => selection.start,
+            false => selection.end,
+        });
+        let Some(m) = nearest_match(&matches, offset, backwards).map(|i| matches[i].clone()) else {
+            return;
+        };
+        let start = text.char_index_from_byte_index(m.start.into());
+        let end = text.char_index_from_byte_index(m.end.into());
+        let mut state = output.state.clone();
+        state.cursor.set_char_range(Some(CCursorRange::two(
+            CCursor::new(start),
+            CCursor::new(end),
+        )));
+        state.store(ui.ctx(), output.response.response.id);
+        if let Some(rect) = crate::hyperlinks::char_rects(output, start.0..end.0).first() {
+            ui.scroll_to_rect(*rect, Some(egui::Align::Center));
+        }
+        ui.ctx().request_repaint();
+    }
+}
@@ -1,19 +1,45 @@
 use crate::syntax::Patch;
 
-use super::{DEFAULT_QUOTES, Syntax};
+use super::{DEFAULT_QUOTES, HEX_DIGITS, Numbers, OCT_DIGITS, Syntax, pairs, words};
 use std::collections::BTreeSet;
 
 impl Syntax {
     pub fn asm() -> Self {
         Syntax {
-            language: "Assembly",
+            language: "Assembly".into(),
             case_sensitive: false,
-            comment: ";",
-            comment_multiline: ["/*", "*/"],
+            comment: ";".into(),
+            comment_multiline: ["/*".into(), "*/".into()],
+            nested_comments: false,
+            alt_comments: words(["#", "//"]),
+            alt_comments_multiline: BTreeSet::new(),
             quotes: DEFAULT_QUOTES.into(),
+            strings: BTreeSet::new(),
+            escapes: BTreeSet::new(),
+            numbers: Numbers::default().with_suffixes([
+                ("h", HEX_DIGITS),
+                ("H", HEX_DIGITS),
+                ("b", "01"),
+                ("B", "01"),
+                ("o", OCT_DIGITS),
+                ("O", OCT_DIGITS),
+                ("q", OCT_DIGITS),
+                ("Q", OCT_DIGITS),
+                ("d", "0123456789"),
+                ("D", "0123456789"),
+            ]),
+            operators: BTreeSet::new(),
+            lifetime: None,
+            attributes: BTreeSet::new(),
+            macro_suffix: None,
+            patterns: Vec::new(),
+            injections: Vec::new(),
+            fold_markers: pairs([["#region", "#endregion"]]),
+            indent_after: BTreeSet::new(),
+            dedent_before: BTreeSet::new(),
             word_start: BTreeSet::new(),
-            hyperlinks: BTreeSet::from(["http:", "https:", "www.", "ftp:", "file:"]),
-            keywords: BTreeSet::from([
+            hyperlinks: words(["http:", "https:", "www.", "ftp:", "file:"]),
+            keywords: words([
                 "vaddpd",
                 "divsd",
                 "vrcp14ps",
@@ -1162,8 +1188,8 @@ impl Syntax {
                 "setno",
                 "vpermb",
             ]),
-            types: BTreeSet::from(["ptr", "byte", "word", "dword", "qword"]),
-            special: BTreeSet::from([
+            types: words(["ptr", "byte", "word", "dword", "qword"]),
+            special: words([
                 "RAX", "RBX", "RCX", "RDX", "RSI", "RDI", "RBP", "RSP", "R8", "R9", "R10", "R11",
                 "R12", "R13", "R14", "R15", // 64-bit registers
                 "EAX", "EBX", "ECX", "EDX", "ESI", "EDI", "EBP", "ESP", "R8D", "R9D", "R10D",
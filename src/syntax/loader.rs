@@ -0,0 +1,94 @@
+//! Loading [`Syntax`] definitions from TOML or JSON files.
+//!
+//! Fields are named as in [`Syntax`], missing ones take values of [`Syntax::default`].
+//! Token types are written as in the serde representation of [`TokenType`](super::TokenType):
+//! `"Special"` or `{ Numeric = false }`.
+//!
+//! ```toml
+//! language = "Notes"
+//! case_sensitive = true
+//! comment = "//"
+//! comment_multiline = ["/*", "*/"]
+//! quotes = ["'", "\""]
+//! keywords = ["let", "if", "else"]
+//! types = ["Date"]
+//! special = ["true", "false"]
+//! operators = ["==", "->"]
+//! patterns = [["@\\w+", "Special"], ["\\d{4}-\\d{2}-\\d{2}", { Numeric = false }]]
+//!
+//! [[strings]]
+//! open = '"""'
+//! close = '"""'
+//! interpolations = [{ open = "${", close = "}" }]
+//!
+//! [[escapes]]
+//! open = "\\"
+//! end = "nt\\\""
+//!
+//! [numbers]
+//! prefixes = [["0x", "0123456789abcdefABCDEF"]]
+//! separators = "_"
+//! ```
+
+use super::Syntax;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+/// Error of loading a [`Syntax`] definition.
+pub enum LoadError {
+    Io(std::io::Error),
+    #[cfg(feature = "toml")]
+    Toml(toml::de::Error),
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+    /// File extension is not `toml` or `json`, or its feature is disabled.
+    Format(PathBuf),
+    #[cfg(feature = "textmate")]
+    /// Grammar is valid JSON, but not a TextMate grammar.
+    Grammar(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "{e}"),
+            #[cfg(feature = "toml")]
+            LoadError::Toml(e) => write!(f, "{e}"),
+            #[cfg(feature = "json")]
+            LoadError::Json(e) => write!(f, "{e}"),
+            LoadError::Format(path) => write!(f, "unknown syntax file format: {}", path.display()),
+            #[cfg(feature = "textmate")]
+            LoadError::Grammar(e) => write!(f, "invalid TextMate grammar: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl Syntax {
+    #[cfg(feature = "toml")]
+    /// Syntax from TOML text.
+    pub fn from_toml(text: &str) -> Result<Self, LoadError> {
+        toml::from_str(text).map_err(LoadError::Toml)
+    }
+
+    #[cfg(feature = "json")]
+    /// Syntax from JSON text.
+    pub fn from_json(text: &str) -> Result<Self, LoadError> {
+        serde_json::from_str(text).map_err(LoadError::Json)
+    }
+
+    /// Syntax from a `.toml` or `.json` file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LoadError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(LoadError::Io)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "toml")]
+            Some("toml") => Syntax::from_toml(&text),
+            #[cfg(feature = "json")]
+            Some("json") => Syntax::from_json(&text),
+            _ => Err(LoadError::Format(path.to_path_buf())),
+        }
+    }
+}
@@ -1,29 +1,63 @@
 use crate::syntax::Patch;
 
-use super::{DEFAULT_QUOTES, Syntax};
+use super::{
+    DEFAULT_QUOTES, Escape, HEX_DIGITS, Numbers, PRINTF_CONVERSIONS, PRINTF_FLAGS, StringDelimiter,
+    Syntax, pairs, words,
+};
 use std::collections::BTreeSet;
 
 impl Syntax {
     pub fn lua() -> Syntax {
         Syntax {
-            language: "Lua",
+            language: "Lua".into(),
             case_sensitive: true,
-            comment: "--",
-            comment_multiline: ["--[[", "]]"],
+            comment: "--".into(),
+            comment_multiline: ["--[[".into(), "]]".into()],
+            nested_comments: false,
+            alt_comments: BTreeSet::new(),
+            alt_comments_multiline: pairs([
+                ["--[=[", "]=]"],
+                ["--[==[", "]==]"],
+                ["--[===[", "]===]"],
+            ]),
             quotes: DEFAULT_QUOTES.into(),
+            strings: BTreeSet::from([StringDelimiter::new("[=[", "]=]")
+                .with_repeat('=')
+                .with_escape(None)]),
+            escapes: BTreeSet::from([
+                Escape::new("\\").with_end("abfnrtvz\\'\""),
+                Escape::new("\\").with_chars("0123456789", 1, 3),
+                Escape::new("\\x").with_chars(HEX_DIGITS, 2, 2),
+                Escape::new("\\u{")
+                    .with_chars(HEX_DIGITS, 1, 8)
+                    .with_end("}"),
+                Escape::new("%")
+                    .with_chars(PRINTF_FLAGS, 0, 8)
+                    .with_end(PRINTF_CONVERSIONS),
+            ]),
+            numbers: Numbers::default().with_prefixes([("0x", HEX_DIGITS), ("0X", HEX_DIGITS)]),
+            operators: words(["..", "...", "//", "::", "<<", "<=", "==", ">=", ">>", "~="]),
+            lifetime: None,
+            attributes: BTreeSet::new(),
+            macro_suffix: None,
+            patterns: Vec::new(),
+            injections: Vec::new(),
+            fold_markers: pairs([["#region", "#endregion"]]),
+            indent_after: words(["{", "(", "[", "then", "do", "else", "repeat"]),
+            dedent_before: words(["}", ")", "]", "end", "else", "elseif", "until"]),
             word_start: BTreeSet::from(['_']),
-            hyperlinks: BTreeSet::from([
+            hyperlinks: words([
                 "http:", "HTTP:", "https:", "HTTPS:", "www.", "WWW.", "ftp:", "FTP:", "file:",
                 "FILE:",
             ]),
-            keywords: BTreeSet::from([
+            keywords: words([
                 "and", "break", "do", "else", "elseif", "end", "for", "function", "if", "in",
                 "local", "not", "or", "repeat", "return", "then", "until", "while",
             ]),
-            types: BTreeSet::from([
+            types: words([
                 "boolean", "number", "string", "function", "userdata", "thread", "table",
             ]),
-            special: BTreeSet::from(["false", "nil", "true"]),
+            special: words(["false", "nil", "true"]),
             patch: Patch::default(),
         }
     }
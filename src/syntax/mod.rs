@@ -1,13 +1,22 @@
 #![allow(dead_code)]
 pub mod asm;
+#[cfg(any(feature = "toml", feature = "json"))]
+pub mod loader;
 pub mod lua;
+pub mod pattern;
 pub mod python;
+pub mod registry;
 pub mod rust;
 pub mod shell;
 pub mod sql;
+#[cfg(feature = "textmate")]
+pub mod textmate;
 
+use pattern::Pattern;
+use std::borrow::Cow;
 use std::collections::BTreeSet;
 use std::hash::{Hash, Hasher};
+use std::ops::Range;
 
 pub const SEPARATORS: [char; 1] = ['_'];
 pub const DEFAULT_QUOTES: [char; 3] = ['\'', '"', '`'];
@@ -21,15 +30,21 @@ type Float = bool;
 #[derive(Default, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub enum TokenType {
+    Attribute,
     Comment(MultiLine),
     Function,
     Keyword,
+    Lifetime,
     Literal,
     Hyperlink,
+    Interpolation,
+    Macro,
     Numeric(Float),
+    Operator,
     Punctuation(char),
     Special,
     Str(char),
+    Escape,
     Type,
     Whitespace(char),
     #[default]
@@ -49,10 +64,14 @@ impl std::fmt::Debug for TokenType {
                     }
                 }
             }
+            TokenType::Attribute => name.push_str("Attribute"),
             TokenType::Function => name.push_str("Function"),
             TokenType::Keyword => name.push_str("Keyword"),
+            TokenType::Lifetime => name.push_str("Lifetime"),
             TokenType::Literal => name.push_str("Literal"),
             TokenType::Hyperlink => name.push_str("Hyperlink"),
+            TokenType::Interpolation => name.push_str("Interpolation"),
+            TokenType::Macro => name.push_str("Macro"),
             TokenType::Numeric(float) => {
                 name.push_str("Numeric");
                 if *float {
@@ -61,12 +80,14 @@ impl std::fmt::Debug for TokenType {
                     name.push_str(" Integer");
                 }
             }
+            TokenType::Operator => name.push_str("Operator"),
             TokenType::Punctuation(_) => name.push_str("Punctuation"),
             TokenType::Special => name.push_str("Special"),
             TokenType::Str(quote) => {
                 name.push_str("Str ");
                 name.push(*quote);
             }
+            TokenType::Escape => name.push_str("Escape"),
             TokenType::Type => name.push_str("Type"),
             TokenType::Whitespace(c) => {
                 name.push_str("Whitespace");
@@ -97,6 +118,8 @@ impl From<(char, &Syntax)> for TokenType {
 }
 
 #[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Patch {
     pub hyperlinks: BTreeSet<String>,
     pub keywords: BTreeSet<String>,
@@ -104,31 +127,516 @@ pub struct Patch {
     pub special: BTreeSet<String>,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+/// String delimiters beyond simple quotes: prefixed, raw, hashed and triple-quoted strings or heredocs.
+pub struct StringDelimiter {
+    /// Prefixes before the opening delimiter, like `b` or `f`. Empty prefix allows none.
+    pub prefixes: BTreeSet<Cow<'static, str>>,
+    pub open: Cow<'static, str>,
+    pub close: Cow<'static, str>,
+    /// Char repeating any number of times where it appears in `open`
+    /// and the same number of times in `close`, like `#` in `r#"..."#` or `=` in `[==[...]==]`.
+    pub repeat: Option<char>,
+    /// Char after which the next one can't close the string.
+    pub escape: Option<char>,
+    /// `open` is followed by a word and the string closes at the line with only that word.
+    pub heredoc: bool,
+    /// Code embedded in the string.
+    pub interpolations: BTreeSet<Interpolation>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// Code inside a string, like `${expr}`, `$name` or `{expr}`.
+/// Doubled `open`, like `{{`, is not an interpolation.
+pub struct Interpolation {
+    pub open: Cow<'static, str>,
+    /// Empty `close` makes interpolation of a single word, like `$name`.
+    pub close: Cow<'static, str>,
+}
+
+impl Interpolation {
+    pub fn new<S: Into<Cow<'static, str>>, E: Into<Cow<'static, str>>>(open: S, close: E) -> Self {
+        Interpolation {
+            open: open.into(),
+            close: close.into(),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+/// Opening delimiter found by [`StringDelimiter::open_at`].
+pub struct Opening {
+    /// Length in bytes, including prefix.
+    pub len: usize,
+    /// Count of repeated chars.
+    pub repeat: usize,
+    /// Byte range of heredoc word.
+    pub word: Range<usize>,
+}
+
+impl Default for StringDelimiter {
+    fn default() -> Self {
+        StringDelimiter::new("", "")
+    }
+}
+
+impl StringDelimiter {
+    pub fn new<S: Into<Cow<'static, str>>, E: Into<Cow<'static, str>>>(open: S, close: E) -> Self {
+        StringDelimiter {
+            prefixes: BTreeSet::from(["".into()]),
+            open: open.into(),
+            close: close.into(),
+            repeat: None,
+            escape: Some('\\'),
+            heredoc: false,
+            interpolations: BTreeSet::new(),
+        }
+    }
+    /// String from `open` and a word till the line with only that word.
+    pub fn heredoc<S: Into<Cow<'static, str>>>(open: S) -> Self {
+        StringDelimiter {
+            escape: None,
+            heredoc: true,
+            ..StringDelimiter::new(open, "")
+        }
+    }
+    pub fn with_prefixes<T: IntoIterator<Item = S>, S: Into<Cow<'static, str>>>(
+        self,
+        prefixes: T,
+    ) -> Self {
+        StringDelimiter {
+            prefixes: words(prefixes),
+            ..self
+        }
+    }
+    pub fn with_repeat(self, repeat: char) -> Self {
+        StringDelimiter {
+            repeat: Some(repeat),
+            ..self
+        }
+    }
+    pub fn with_escape(self, escape: Option<char>) -> Self {
+        StringDelimiter { escape, ..self }
+    }
+    pub fn with_interpolations<T: Into<BTreeSet<Interpolation>>>(self, interpolations: T) -> Self {
+        StringDelimiter {
+            interpolations: interpolations.into(),
+            ..self
+        }
+    }
+
+    /// Opening delimiter at the start of `text`.
+    pub fn open_at(&self, text: &str) -> Option<Opening> {
+        if self.open.is_empty() {
+            return None;
+        }
+        self.prefixes
+            .iter()
+            .filter_map(|prefix| {
+                let mut opening = Opening {
+                    len: prefix.len(),
+                    ..Default::default()
+                };
+                let mut rest = text.strip_prefix(prefix.as_ref())?;
+                for c in self.open.chars() {
+                    if Some(c) == self.repeat {
+                        let repeated = rest.len() - rest.trim_start_matches(c).len();
+                        opening.repeat = repeated / c.len_utf8();
+                        opening.len += repeated;
+                        rest = &rest[repeated..];
+                    } else {
+                        rest = rest.strip_prefix(c)?;
+                        opening.len += c.len_utf8();
+                    }
+                }
+                if self.heredoc {
+                    let spaces = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+                    let quote = rest[spaces..].starts_with(['\'', '"']) as usize;
+                    let start = opening.len + spaces + quote;
+                    let word = text[start..]
+                        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                        .unwrap_or(text.len() - start);
+                    if word == 0 || text[start..].starts_with(|c: char| c.is_numeric()) {
+                        return None;
+                    }
+                    opening.word = start..start + word;
+                    opening.len = start + word + quote;
+                }
+                Some(opening)
+            })
+            .max_by_key(|opening| opening.len)
+    }
+
+    /// Does `body` end with the closing delimiter.
+    pub fn closes(&self, body: &str, repeat: usize) -> bool {
+        let mut body = body.chars().rev();
+        self.close.chars().rev().all(|c| {
+            let count = if Some(c) == self.repeat { repeat } else { 1 };
+            (0..count).all(|_| body.next() == Some(c))
+        })
+    }
+
+    /// Is `text` starting with doubled opening of interpolation, like `{{`.
+    pub fn doubled_at(&self, text: &str) -> bool {
+        self.interpolations.iter().any(|i| {
+            !i.close.is_empty()
+                && text
+                    .strip_prefix(i.open.as_ref())
+                    .is_some_and(|rest| rest.starts_with(i.open.as_ref()))
+        })
+    }
+
+    /// Interpolation at the start of `text`, closed on the same line:
+    /// lengths of opening delimiter, code and closing delimiter.
+    pub fn interpolation_at(&self, text: &str) -> Option<(usize, usize, usize)> {
+        let stop = |c: char| c == '\n' || Some(c) == self.escape || self.close.starts_with(c);
+        self.interpolations.iter().find_map(|i| {
+            let code = text.strip_prefix(i.open.as_ref())?;
+            if i.close.is_empty() {
+                let len = code
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(code.len());
+                return (len > 0).then_some((i.open.len(), len, 0));
+            }
+            let nested = i.open.chars().last();
+            let mut depth = 0;
+            for (n, c) in code.char_indices() {
+                if code[n..].starts_with(i.close.as_ref()) {
+                    if depth == 0 {
+                        return Some((i.open.len(), n, i.close.len()));
+                    }
+                    depth -= 1;
+                } else if stop(c) {
+                    return None;
+                } else if Some(c) == nested {
+                    depth += 1;
+                }
+            }
+            None
+        })
+    }
+
+    /// Char for [`TokenType::Str`].
+    pub fn quote(&self) -> char {
+        self.open.chars().last().unwrap_or_default()
+    }
+}
+
+#[derive(Default, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+/// Escape sequence in strings: `open`, then `min..=max` of `chars`, then one of `end` chars if any.
+///
+/// `\x41` is `Escape::new("\\x").with_chars(HEX_DIGITS, 2, 2)`,
+/// `\u{1F600}` is `Escape::new("\\u{").with_chars(HEX_DIGITS, 1, 6).with_end("}")`.
+pub struct Escape {
+    pub open: Cow<'static, str>,
+    pub chars: Cow<'static, str>,
+    pub min: usize,
+    pub max: usize,
+    pub end: Cow<'static, str>,
+}
+
+pub const HEX_DIGITS: &str = "0123456789abcdefABCDEF";
+pub const OCT_DIGITS: &str = "01234567";
+/// Flags, width, precision and length of printf-like `%` conversions.
+pub const PRINTF_FLAGS: &str = "-+ #0123456789.*hlLqjzt";
+pub const PRINTF_CONVERSIONS: &str = "diouxXeEfFgGaAcspn%";
+
+impl Escape {
+    pub fn new<S: Into<Cow<'static, str>>>(open: S) -> Self {
+        Escape {
+            open: open.into(),
+            ..Default::default()
+        }
+    }
+    pub fn with_chars<S: Into<Cow<'static, str>>>(self, chars: S, min: usize, max: usize) -> Self {
+        Escape {
+            chars: chars.into(),
+            min,
+            max,
+            ..self
+        }
+    }
+    pub fn with_end<S: Into<Cow<'static, str>>>(self, end: S) -> Self {
+        Escape {
+            end: end.into(),
+            ..self
+        }
+    }
+
+    /// Length of this escape sequence at the start of `text`, never crossing a line break.
+    pub fn len_at(&self, text: &str) -> Option<usize> {
+        let rest = text.strip_prefix(self.open.as_ref())?;
+        let mut len = self.open.len();
+        let mut count = 0;
+        for c in rest.chars() {
+            if count == self.max || c == '\n' || !self.chars.contains(c) {
+                break;
+            }
+            count += 1;
+            len += c.len_utf8();
+        }
+        if count < self.min {
+            return None;
+        }
+        if !self.end.is_empty() {
+            let c = text[len..]
+                .chars()
+                .next()
+                .filter(|&c| c != '\n' && self.end.contains(c))?;
+            len += c.len_utf8();
+        }
+        (len > 0).then_some(len)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+/// Rules for numeric literals.
+///
+/// A literal starts with a decimal digit and ends with an optional type suffix,
+/// like `f32` in `3.14_f32`: letters, digits and `_` right after the number.
+/// Radix prefixes are opt-in, see [`Numbers::RADIX_PREFIXES`]. A prefix or an exponent marker
+/// without digits after it, like `0xg` or `1e`, is not a number.
+pub struct Numbers {
+    /// Radix prefixes and their digits, like `0x` with hexadecimal digits.
+    pub prefixes: BTreeSet<(Cow<'static, str>, Cow<'static, str>)>,
+    /// Radix suffixes and their digits, like `h` with hexadecimal digits in `0FFh`.
+    pub suffixes: BTreeSet<(Cow<'static, str>, Cow<'static, str>)>,
+    /// Exponent markers of decimal literals, like `e` in `1e-9`.
+    pub exponents: Cow<'static, str>,
+    /// Digit separators, like `_` in `1_000_000`.
+    pub separators: Cow<'static, str>,
+}
+impl Default for Numbers {
+    fn default() -> Self {
+        Numbers {
+            prefixes: BTreeSet::new(),
+            suffixes: BTreeSet::new(),
+            exponents: "eE".into(),
+            separators: "_".into(),
+        }
+    }
+}
+impl Numbers {
+    /// `0x`, `0o` and `0b` prefixes in both cases, as in C-like languages.
+    pub const RADIX_PREFIXES: [(&str, &str); 6] = [
+        ("0x", HEX_DIGITS),
+        ("0X", HEX_DIGITS),
+        ("0o", OCT_DIGITS),
+        ("0O", OCT_DIGITS),
+        ("0b", "01"),
+        ("0B", "01"),
+    ];
+
+    /// Radix prefixes and their digits.
+    pub fn with_prefixes<T, A, D>(self, prefixes: T) -> Self
+    where
+        T: IntoIterator<Item = (A, D)>,
+        A: Into<Cow<'static, str>>,
+        D: Into<Cow<'static, str>>,
+    {
+        Numbers {
+            prefixes: radixes(prefixes),
+            ..self
+        }
+    }
+    /// Radix suffixes and their digits.
+    pub fn with_suffixes<T, A, D>(self, suffixes: T) -> Self
+    where
+        T: IntoIterator<Item = (A, D)>,
+        A: Into<Cow<'static, str>>,
+        D: Into<Cow<'static, str>>,
+    {
+        Numbers {
+            suffixes: radixes(suffixes),
+            ..self
+        }
+    }
+    pub fn with_exponents<S: Into<Cow<'static, str>>>(self, exponents: S) -> Self {
+        Numbers {
+            exponents: exponents.into(),
+            ..self
+        }
+    }
+    pub fn with_separators<S: Into<Cow<'static, str>>>(self, separators: S) -> Self {
+        Numbers {
+            separators: separators.into(),
+            ..self
+        }
+    }
+
+    /// Numeric literal at the start of `text`: its length and whether it is a float.
+    pub fn len_at(&self, text: &str) -> Option<(usize, bool)> {
+        if !text.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        let digits = |text: &str, digits: &str| {
+            text.find(|c: char| !(digits.contains(c) || self.separators.contains(c)))
+                .unwrap_or(text.len())
+        };
+        let suffix = |text: &str| {
+            text.find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(text.len())
+        };
+        let mut prefixed = false;
+        for (prefix, radix) in self.prefixes.iter().rev() {
+            if let Some(rest) = text.strip_prefix(prefix.as_ref()) {
+                if rest.starts_with(|c: char| radix.contains(c)) {
+                    let len = prefix.len() + digits(rest, radix);
+                    return Some((len + suffix(&text[len..]), false));
+                }
+                prefixed = true;
+            }
+        }
+        if prefixed {
+            return None;
+        }
+        let word = &text[..suffix(text)];
+        for (suffix, radix) in &self.suffixes {
+            if let Some(number) = word.strip_suffix(suffix.as_ref())
+                && digits(number, radix) == number.len()
+            {
+                return Some((word.len(), false));
+            }
+        }
+        let mut len = digits(text, "0123456789");
+        let mut float = false;
+        let fraction = |len: usize| {
+            text[len..]
+                .strip_prefix('.')
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        };
+        if fraction(len) {
+            len += 1 + digits(&text[len + 1..], "0123456789");
+            float = true;
+        }
+        if let Some(e) = text[len..].chars().next()
+            && self.exponents.contains(e)
+        {
+            let rest = &text[len + e.len_utf8()..];
+            let sign = rest.starts_with(['+', '-']) as usize;
+            if !rest[sign..].starts_with(|c: char| c.is_ascii_digit()) {
+                return None;
+            }
+            len += e.len_utf8() + sign;
+            len += digits(&text[len..], "0123456789");
+            float = true;
+        }
+        Some((len + suffix(&text[len..]), float))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// Text lexed with another syntax: a region between delimiters, like a code fence,
+/// or a string after a comment with the marker, like `/* sql */ "SELECT 1"`.
+pub struct Injection {
+    /// Region delimiters, lexed by the host syntax as a string. `None` for marked strings.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub region: Option<StringDelimiter>,
+    /// Comment text marking the next string, compared ignoring case and surrounding spaces.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub marker: Cow<'static, str>,
+    pub syntax: Syntax,
+}
+
+impl Injection {
+    /// Region from `open` to `close`, without escapes.
+    pub fn region<S: Into<Cow<'static, str>>, E: Into<Cow<'static, str>>>(
+        open: S,
+        close: E,
+        syntax: Syntax,
+    ) -> Self {
+        Injection {
+            region: Some(StringDelimiter::new(open, close).with_escape(None)),
+            marker: "".into(),
+            syntax,
+        }
+    }
+    /// String after a comment with `marker`.
+    pub fn marked<S: Into<Cow<'static, str>>>(marker: S, syntax: Syntax) -> Self {
+        Injection {
+            region: None,
+            marker: marker.into(),
+            syntax,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 /// Rules for highlighting.
 pub struct Syntax {
-    pub language: &'static str,
+    pub language: Cow<'static, str>,
     pub case_sensitive: bool,
-    pub comment: &'static str,
-    pub comment_multiline: [&'static str; 2],
+    pub comment: Cow<'static, str>,
+    pub comment_multiline: [Cow<'static, str>; 2],
+    pub nested_comments: bool,
+    pub alt_comments: BTreeSet<Cow<'static, str>>,
+    pub alt_comments_multiline: BTreeSet<[Cow<'static, str>; 2]>,
     pub quotes: BTreeSet<char>,
+    pub strings: BTreeSet<StringDelimiter>,
+    /// Escape sequences in strings with an escape char.
+    pub escapes: BTreeSet<Escape>,
+    pub numbers: Numbers,
+    /// Operators lexed as single tokens, longest first.
+    pub operators: BTreeSet<Cow<'static, str>>,
+    /// Quote starting a lifetime or label, like `'a`, unless it closes a char literal like `'a'`.
+    pub lifetime: Option<char>,
+    /// Attribute openers, like `#[`, lexed together with the attribute path.
+    pub attributes: BTreeSet<Cow<'static, str>>,
+    /// Char after a word making it a macro invocation, like `!` in `println!`.
+    pub macro_suffix: Option<char>,
+    /// Rules tried in order at the start of every token, before the built-in ones.
+    pub patterns: Vec<(Pattern, TokenType)>,
+    /// Regions and marked strings lexed with other syntaxes.
+    pub injections: Vec<Injection>,
+    /// Lines opening and closing fold regions, like `#region` and `#endregion`,
+    /// matched at the line start or after a line comment marker.
+    pub fold_markers: BTreeSet<[Cow<'static, str>; 2]>,
+    /// Line endings indenting the next line, like `{` or `then`.
+    pub indent_after: BTreeSet<Cow<'static, str>>,
+    /// Line starts dedenting their line, like `}` or `end`.
+    pub dedent_before: BTreeSet<Cow<'static, str>>,
     pub word_start: BTreeSet<char>,
-    pub hyperlinks: BTreeSet<&'static str>,
-    pub keywords: BTreeSet<&'static str>,
-    pub types: BTreeSet<&'static str>,
-    pub special: BTreeSet<&'static str>,
+    pub hyperlinks: BTreeSet<Cow<'static, str>>,
+    pub keywords: BTreeSet<Cow<'static, str>>,
+    pub types: BTreeSet<Cow<'static, str>>,
+    pub special: BTreeSet<Cow<'static, str>>,
     pub patch: Patch,
 }
 impl Default for Syntax {
     fn default() -> Self {
         Syntax {
-            language: "default",
+            language: "default".into(),
             case_sensitive: true,
-            comment: "//",
-            comment_multiline: ["/*", "*/"],
+            comment: "//".into(),
+            comment_multiline: ["/*".into(), "*/".into()],
+            nested_comments: false,
+            alt_comments: BTreeSet::new(),
+            alt_comments_multiline: BTreeSet::new(),
             quotes: DEFAULT_QUOTES.into(),
+            strings: BTreeSet::new(),
+            escapes: BTreeSet::new(),
+            numbers: Numbers::default(),
+            operators: BTreeSet::new(),
+            lifetime: None,
+            attributes: BTreeSet::new(),
+            macro_suffix: None,
+            patterns: Vec::new(),
+            injections: Vec::new(),
+            fold_markers: BTreeSet::new(),
+            indent_after: words(["{", "(", "["]),
+            dedent_before: words(["}", ")", "]"]),
             word_start: BTreeSet::new(),
-            hyperlinks: BTreeSet::from(["http"]),
+            hyperlinks: words(["http"]),
             keywords: BTreeSet::new(),
             types: BTreeSet::new(),
             special: BTreeSet::new(),
@@ -142,9 +650,9 @@ impl Hash for Syntax {
     }
 }
 impl Syntax {
-    pub fn new(language: &'static str) -> Self {
+    pub fn new<S: Into<Cow<'static, str>>>(language: S) -> Self {
         Syntax {
-            language,
+            language: language.into(),
             quotes: DEFAULT_QUOTES.into(),
             ..Default::default()
         }
@@ -155,12 +663,51 @@ impl Syntax {
             ..self
         }
     }
-    pub fn with_comment(self, comment: &'static str) -> Self {
-        Syntax { comment, ..self }
+    pub fn with_comment<S: Into<Cow<'static, str>>>(self, comment: S) -> Self {
+        Syntax {
+            comment: comment.into(),
+            ..self
+        }
     }
-    pub fn with_comment_multiline(self, comment_multiline: [&'static str; 2]) -> Self {
+    pub fn with_comment_multiline<S: Into<Cow<'static, str>>>(
+        self,
+        comment_multiline: [S; 2],
+    ) -> Self {
         Syntax {
-            comment_multiline,
+            comment_multiline: comment_multiline.map(Into::into),
+            ..self
+        }
+    }
+    /// Multiline comments may contain other multiline comments, like `/* /* */ */` in Rust.
+    pub fn with_nested_comments(self, nested_comments: bool) -> Self {
+        Syntax {
+            nested_comments,
+            ..self
+        }
+    }
+    /// Line comment markers besides the main one.
+    pub fn with_alt_comments<T: IntoIterator<Item = S>, S: Into<Cow<'static, str>>>(
+        self,
+        alt_comments: T,
+    ) -> Self {
+        Syntax {
+            alt_comments: words(alt_comments),
+            ..self
+        }
+    }
+    /// Multiline comment delimiters besides the main pair.
+    pub fn with_alt_comments_multiline<
+        T: IntoIterator<Item = [S; 2]>,
+        S: Into<Cow<'static, str>>,
+    >(
+        self,
+        alt_comments_multiline: T,
+    ) -> Self {
+        Syntax {
+            alt_comments_multiline: alt_comments_multiline
+                .into_iter()
+                .map(|pair| pair.map(Into::into))
+                .collect(),
             ..self
         }
     }
@@ -170,42 +717,284 @@ impl Syntax {
             ..self
         }
     }
+    pub fn with_strings<T: Into<BTreeSet<StringDelimiter>>>(self, strings: T) -> Self {
+        Syntax {
+            strings: strings.into(),
+            ..self
+        }
+    }
+    pub fn with_escapes<T: Into<BTreeSet<Escape>>>(self, escapes: T) -> Self {
+        Syntax {
+            escapes: escapes.into(),
+            ..self
+        }
+    }
+    pub fn with_numbers(self, numbers: Numbers) -> Self {
+        Syntax { numbers, ..self }
+    }
+    pub fn with_operators<T: IntoIterator<Item = S>, S: Into<Cow<'static, str>>>(
+        self,
+        operators: T,
+    ) -> Self {
+        Syntax {
+            operators: words(operators),
+            ..self
+        }
+    }
+    pub fn with_lifetime(self, lifetime: Option<char>) -> Self {
+        Syntax { lifetime, ..self }
+    }
+    pub fn with_attributes<T: IntoIterator<Item = S>, S: Into<Cow<'static, str>>>(
+        self,
+        attributes: T,
+    ) -> Self {
+        Syntax {
+            attributes: words(attributes),
+            ..self
+        }
+    }
+    pub fn with_macro_suffix(self, macro_suffix: Option<char>) -> Self {
+        Syntax {
+            macro_suffix,
+            ..self
+        }
+    }
+    pub fn with_patterns<T: Into<Vec<(Pattern, TokenType)>>>(self, patterns: T) -> Self {
+        Syntax {
+            patterns: patterns.into(),
+            ..self
+        }
+    }
+    pub fn with_injections<T: Into<Vec<Injection>>>(self, injections: T) -> Self {
+        Syntax {
+            injections: injections.into(),
+            ..self
+        }
+    }
+    pub fn with_fold_markers<T: IntoIterator<Item = [S; 2]>, S: Into<Cow<'static, str>>>(
+        self,
+        fold_markers: T,
+    ) -> Self {
+        Syntax {
+            fold_markers: fold_markers
+                .into_iter()
+                .map(|pair| pair.map(Into::into))
+                .collect(),
+            ..self
+        }
+    }
+    pub fn with_indent_after<T: IntoIterator<Item = S>, S: Into<Cow<'static, str>>>(
+        self,
+        indent_after: T,
+    ) -> Self {
+        Syntax {
+            indent_after: words(indent_after),
+            ..self
+        }
+    }
+    pub fn with_dedent_before<T: IntoIterator<Item = S>, S: Into<Cow<'static, str>>>(
+        self,
+        dedent_before: T,
+    ) -> Self {
+        Syntax {
+            dedent_before: words(dedent_before),
+            ..self
+        }
+    }
     pub fn with_word_start<T: Into<BTreeSet<char>>>(self, word_start: T) -> Self {
         Syntax {
             word_start: word_start.into(),
             ..self
         }
     }
-    pub fn with_hyperlinks<T: Into<BTreeSet<&'static str>>>(self, hyperlinks: T) -> Self {
+    pub fn with_hyperlinks<T: IntoIterator<Item = S>, S: Into<Cow<'static, str>>>(
+        self,
+        hyperlinks: T,
+    ) -> Self {
         Syntax {
-            hyperlinks: hyperlinks.into(),
+            hyperlinks: words(hyperlinks),
             ..self
         }
     }
-    pub fn with_keywords<T: Into<BTreeSet<&'static str>>>(self, keywords: T) -> Self {
+    pub fn with_keywords<T: IntoIterator<Item = S>, S: Into<Cow<'static, str>>>(
+        self,
+        keywords: T,
+    ) -> Self {
         Syntax {
-            keywords: keywords.into(),
+            keywords: words(keywords),
             ..self
         }
     }
-    pub fn with_types<T: Into<BTreeSet<&'static str>>>(self, types: T) -> Self {
+    pub fn with_types<T: IntoIterator<Item = S>, S: Into<Cow<'static, str>>>(
+        self,
+        types: T,
+    ) -> Self {
         Syntax {
-            types: types.into(),
+            types: words(types),
             ..self
         }
     }
-    pub fn with_special<T: Into<BTreeSet<&'static str>>>(self, special: T) -> Self {
+    pub fn with_special<T: IntoIterator<Item = S>, S: Into<Cow<'static, str>>>(
+        self,
+        special: T,
+    ) -> Self {
         Syntax {
-            special: special.into(),
+            special: words(special),
             ..self
         }
     }
 
     pub fn language(&self) -> &str {
-        self.language
+        &self.language
     }
     pub fn comment(&self) -> &str {
-        self.comment
+        &self.comment
+    }
+    /// All line comment markers, the main one first.
+    pub fn comments(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(&self.comment)
+            .chain(&self.alt_comments)
+            .map(AsRef::as_ref)
+    }
+    /// All multiline comment delimiters, the main pair first.
+    pub fn comments_multiline(&self) -> impl Iterator<Item = [&str; 2]> {
+        std::iter::once(&self.comment_multiline)
+            .chain(&self.alt_comments_multiline)
+            .map(|[open, close]| [open.as_ref(), close.as_ref()])
+    }
+    /// Longest comment marker at the start of `text`:
+    /// whether the comment is multiline, index of its delimiters and marker length.
+    pub fn comment_at(&self, text: &str) -> Option<(bool, usize, usize)> {
+        let line = self
+            .comments()
+            .filter(|m| !m.is_empty() && text.starts_with(m))
+            .map(|m| (false, 0, m.len()));
+        let multiline = self
+            .comments_multiline()
+            .enumerate()
+            .filter(|(_, [open, _])| !open.is_empty() && text.starts_with(open))
+            .map(|(i, [open, _])| (true, i, open.len()));
+        line.chain(multiline)
+            .reduce(|longest, m| if m.2 > longest.2 { m } else { longest })
+    }
+    /// String delimiters, followed by delimiters of injection regions.
+    pub fn delimiters(&self) -> impl Iterator<Item = &StringDelimiter> {
+        self.strings
+            .iter()
+            .chain(self.injections.iter().filter_map(|i| i.region.as_ref()))
+    }
+    /// Longest string opening delimiter at the start of `text` and its index in [`Syntax::delimiters`].
+    pub fn string_at(&self, text: &str) -> Option<(usize, Opening)> {
+        self.delimiters()
+            .enumerate()
+            .filter_map(|(i, delimiter)| delimiter.open_at(text).map(|opening| (i, opening)))
+            .max_by_key(|(_, opening)| opening.len)
+    }
+    /// Byte range of the contents of `string`, without its delimiters.
+    pub fn string_body(&self, string: &str) -> Range<usize> {
+        let string_at = self
+            .string_at(string)
+            .and_then(|(i, opening)| Some((self.delimiters().nth(i)?, opening)));
+        let (open, close) = match string_at {
+            Some((delimiter, opening)) => {
+                let close = delimiter
+                    .close
+                    .chars()
+                    .map(|c| match Some(c) == delimiter.repeat {
+                        true => c.len_utf8() * opening.repeat,
+                        false => c.len_utf8(),
+                    })
+                    .sum();
+                let closed =
+                    string.len() >= opening.len + close && delimiter.closes(string, opening.repeat);
+                (opening.len, if closed { close } else { 0 })
+            }
+            None => match string.chars().next() {
+                Some(q) if self.quotes.contains(&q) => {
+                    let closed = string.len() > q.len_utf8() && string.ends_with(q);
+                    (q.len_utf8(), if closed { q.len_utf8() } else { 0 })
+                }
+                _ => (0, 0),
+            },
+        };
+        open..string.len() - close
+    }
+    /// Injection of the string or region starting `string`, after a comment marking `marked`.
+    pub fn injection_at<'a>(
+        &'a self,
+        string: &str,
+        marked: Option<&'a Injection>,
+    ) -> Option<&'a Injection> {
+        match self.string_at(string) {
+            Some((i, _)) if i >= self.strings.len() => self
+                .injections
+                .iter()
+                .filter(|injection| injection.region.is_some())
+                .nth(i - self.strings.len()),
+            _ => marked,
+        }
+    }
+    /// Injection marked by `comment`, like `/* sql */`, for the next string.
+    pub fn marked_by(&self, comment: &str) -> Option<&Injection> {
+        let body = self
+            .comments_multiline()
+            .filter(|[open, _]| !open.is_empty())
+            .find_map(|[open, close]| {
+                let body = comment.strip_prefix(open)?;
+                Some(body.strip_suffix(close).unwrap_or(body))
+            })
+            .or_else(|| {
+                self.comments()
+                    .filter(|m| !m.is_empty())
+                    .find_map(|m| comment.strip_prefix(m))
+            })?;
+        self.injections.iter().find(|i| {
+            i.region.is_none() && !i.marker.is_empty() && i.marker.eq_ignore_ascii_case(body.trim())
+        })
+    }
+    /// Length of the longest escape sequence at the start of `text`.
+    pub fn escape_at(&self, text: &str) -> Option<usize> {
+        self.escapes.iter().filter_map(|e| e.len_at(text)).max()
+    }
+    /// Length of the longest operator at the start of `text`.
+    pub fn operator_at(&self, text: &str) -> Option<usize> {
+        self.operators
+            .iter()
+            .filter(|o| !o.is_empty() && text.starts_with(o.as_ref()))
+            .map(|o| o.len())
+            .max()
+    }
+    /// First pattern rule matching `text` at byte `at`: length of the match and its type.
+    pub fn pattern_at(&self, text: &str, at: usize) -> Option<(usize, TokenType)> {
+        let line_start = text[..at].ends_with('\n') || at == 0;
+        self.patterns
+            .iter()
+            .find_map(|(pattern, ty)| Some((pattern.len_at(&text[at..], line_start)?, *ty)))
+    }
+    /// Length of a lifetime at the start of `text`.
+    pub fn lifetime_at(&self, text: &str) -> Option<usize> {
+        let quote = self.lifetime?;
+        let name = text.strip_prefix(quote)?;
+        if name.starts_with(|c: char| c.is_numeric()) {
+            return None;
+        }
+        let len = name
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(name.len());
+        (len > 0 && !name[len..].starts_with(quote)).then_some(quote.len_utf8() + len)
+    }
+    /// Length of an attribute opener with the attribute path at the start of `text`.
+    pub fn attribute_at(&self, text: &str) -> Option<usize> {
+        let open = self
+            .attributes
+            .iter()
+            .filter(|a| !a.is_empty() && text.starts_with(a.as_ref()))
+            .map(|a| a.len())
+            .max()?;
+        let path = text[open..]
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+            .unwrap_or(text.len() - open);
+        Some(open + path)
     }
     pub fn is_word_start(&self, c: &char) -> bool {
         self.word_start.contains(c)
@@ -219,7 +1008,7 @@ impl Syntax {
     }
     pub fn is_keyword(&self, word: &str) -> bool {
         if self.case_sensitive {
-            self.keywords.contains(&word) || self.patch.keywords.contains(word)
+            self.keywords.contains(word) || self.patch.keywords.contains(word)
         } else {
             self.keywords.contains(word.to_ascii_uppercase().as_str())
                 || self
@@ -230,7 +1019,7 @@ impl Syntax {
     }
     pub fn is_type(&self, word: &str) -> bool {
         if self.case_sensitive {
-            self.types.contains(&word) || self.patch.types.contains(word)
+            self.types.contains(word) || self.patch.types.contains(word)
         } else {
             self.types.contains(word.to_ascii_uppercase().as_str())
                 || self
@@ -241,7 +1030,7 @@ impl Syntax {
     }
     pub fn is_special(&self, word: &str) -> bool {
         if self.case_sensitive {
-            self.special.contains(&word) || self.patch.special.contains(word)
+            self.special.contains(word) || self.patch.special.contains(word)
         } else {
             self.special.contains(word.to_ascii_uppercase().as_str())
                 || self
@@ -253,13 +1042,29 @@ impl Syntax {
 }
 
 impl Syntax {
-    pub fn simple(comment: &'static str) -> Self {
+    pub fn simple<S: Into<Cow<'static, str>>>(comment: S) -> Self {
+        let comment = comment.into();
         Syntax {
-            language: "",
+            language: "".into(),
             case_sensitive: false,
+            comment_multiline: [comment.clone(), comment.clone()],
             comment,
-            comment_multiline: [comment; 2],
+            nested_comments: false,
+            alt_comments: BTreeSet::new(),
+            alt_comments_multiline: BTreeSet::new(),
             quotes: DEFAULT_QUOTES.into(),
+            strings: BTreeSet::new(),
+            escapes: BTreeSet::new(),
+            numbers: Numbers::default(),
+            operators: BTreeSet::new(),
+            lifetime: None,
+            attributes: BTreeSet::new(),
+            macro_suffix: None,
+            patterns: Vec::new(),
+            injections: Vec::new(),
+            fold_markers: BTreeSet::new(),
+            indent_after: BTreeSet::new(),
+            dedent_before: BTreeSet::new(),
             word_start: BTreeSet::new(),
             hyperlinks: BTreeSet::new(),
             keywords: BTreeSet::new(),
@@ -269,3 +1074,28 @@ impl Syntax {
         }
     }
 }
+
+/// Set of owned or static strings.
+fn words<T: IntoIterator<Item = S>, S: Into<Cow<'static, str>>>(
+    words: T,
+) -> BTreeSet<Cow<'static, str>> {
+    words.into_iter().map(Into::into).collect()
+}
+
+/// Set of radix affixes with their digits.
+fn radixes<T, A, D>(radixes: T) -> BTreeSet<(Cow<'static, str>, Cow<'static, str>)>
+where
+    T: IntoIterator<Item = (A, D)>,
+    A: Into<Cow<'static, str>>,
+    D: Into<Cow<'static, str>>,
+{
+    radixes
+        .into_iter()
+        .map(|(affix, digits)| (affix.into(), digits.into()))
+        .collect()
+}
+
+/// Set of owned or static delimiter pairs.
+fn pairs<T: IntoIterator<Item = [&'static str; 2]>>(pairs: T) -> BTreeSet<[Cow<'static, str>; 2]> {
+    pairs.into_iter().map(|pair| pair.map(Into::into)).collect()
+}
@@ -0,0 +1,409 @@
+//! Small regular expressions for [`Syntax::patterns`](super::Syntax::patterns).
+//!
+//! Supported: literal chars, `.`, classes like `[a-z_]` and `[^0-9]`, `\d`, `\w`, `\s`
+//! and their negations, groups with alternatives `(a|b)`, quantifiers `*`, `+`, `?`,
+//! `{n}`, `{n,}`, `{n,m}`, anchors `^` and `$` for the start and the end of the line
+//! and `\b` for a word boundary, with the token start counting as a non-word char before it.
+//! Quantifiers are greedy. Matches never cross a line break.
+//! Matching gives up after [`STEP_LIMIT`] steps, so patterns like `(a*)*b` can't hang,
+//! and on repeated groups nested deeper than [`DEPTH_LIMIT`], so long lines can't overflow the stack.
+//! Repeated single chars, like `.*`, match lines of any length.
+//! Case-insensitive patterns are made with [`Pattern::with_case_insensitive`].
+
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::fmt;
+
+/// Steps a match may take before it counts as no match.
+pub const STEP_LIMIT: usize = 100_000;
+/// Nesting of matched nodes before a match counts as no match.
+pub const DEPTH_LIMIT: usize = 1_000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Atom {
+    Any,
+    Char(char),
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+    Class(bool, Vec<Atom>),
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Any => true,
+            Atom::Char(x) => c == *x,
+            Atom::Range(a, b) => (*a..=*b).contains(&c),
+            Atom::Digit(negated) => c.is_ascii_digit() != *negated,
+            Atom::Word(negated) => (c.is_alphanumeric() || c == '_') != *negated,
+            Atom::Space(negated) => c.is_whitespace() != *negated,
+            Atom::Class(negated, atoms) => atoms.iter().any(|a| a.matches(c)) != *negated,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Node {
+    Atom(Atom),
+    Start,
+    End,
+    Boundary,
+    Group(Vec<Vec<Node>>),
+    Repeat(Box<Node>, usize, usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Error in pattern source at char `position`.
+pub struct PatternError {
+    pub position: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+#[derive(Clone, Debug)]
+/// Compiled pattern, equal to another one with the same source.
+pub struct Pattern {
+    source: Cow<'static, str>,
+    alternatives: Vec<Vec<Node>>,
+    case_insensitive: bool,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Pattern {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Pattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Pattern::new(source).map_err(serde::de::Error::custom)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source && self.case_insensitive == other.case_insensitive
+    }
+}
+
+impl Pattern {
+    pub fn new<S: Into<Cow<'static, str>>>(source: S) -> Result<Self, PatternError> {
+        let source = source.into();
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            position: 0,
+        };
+        let alternatives = parser.alternatives()?;
+        if parser.position < parser.chars.len() {
+            return Err(parser.error("unmatched `)`"));
+        }
+        Ok(Pattern {
+            source,
+            alternatives,
+            case_insensitive: false,
+        })
+    }
+
+    /// Match chars ignoring their case.
+    pub fn with_case_insensitive(self, case_insensitive: bool) -> Self {
+        Pattern {
+            case_insensitive,
+            ..self
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Length of the match at the start of `text`, if not empty and found within [`STEP_LIMIT`].
+    /// `line_start` tells whether `text` starts a line, for `^`.
+    pub fn len_at(&self, text: &str, line_start: bool) -> Option<usize> {
+        let line = text.split('\n').next().unwrap_or_default();
+        let mut len = None;
+        let matcher = Matcher {
+            line,
+            line_start,
+            case_insensitive: self.case_insensitive,
+            steps: Cell::new(0),
+            depth: Cell::new(0),
+        };
+        self.alternatives.iter().any(|seq| {
+            matcher.seq(seq, 0, &mut |end| {
+                len = Some(end);
+                true
+            })
+        });
+        len.filter(|&len| len > 0 && matcher.steps.get() <= STEP_LIMIT)
+    }
+}
+
+struct Matcher<'a> {
+    line: &'a str,
+    line_start: bool,
+    case_insensitive: bool,
+    /// Steps taken, over [`STEP_LIMIT`] once given up.
+    steps: Cell<usize>,
+    /// Nodes being matched.
+    depth: Cell<usize>,
+}
+
+impl Matcher<'_> {
+    fn seq(&self, nodes: &[Node], pos: usize, k: &mut dyn FnMut(usize) -> bool) -> bool {
+        let Some((node, rest)) = nodes.split_first() else {
+            return k(pos);
+        };
+        match node {
+            Node::Repeat(inner, min, max) => self.repeat(inner, *min, *max, rest, pos, k),
+            _ => self.one(node, pos, &mut |end| self.seq(rest, end, k)),
+        }
+    }
+
+    /// Count a step, giving up over the limits.
+    fn step(&self) -> bool {
+        self.steps.set(self.steps.get() + 1);
+        if self.depth.get() > DEPTH_LIMIT {
+            self.steps.set(STEP_LIMIT + 1);
+        }
+        self.steps.get() <= STEP_LIMIT
+    }
+
+    fn matches(&self, atom: &Atom, c: char) -> bool {
+        atom.matches(c)
+            || self.case_insensitive
+                && c.to_lowercase()
+                    .chain(c.to_uppercase())
+                    .any(|c| atom.matches(c))
+    }
+
+    fn one(&self, node: &Node, pos: usize, k: &mut dyn FnMut(usize) -> bool) -> bool {
+        if !self.step() {
+            return false;
+        }
+        self.depth.set(self.depth.get() + 1);
+        let found = self.node(node, pos, k);
+        self.depth.set(self.depth.get() - 1);
+        found
+    }
+
+    fn node(&self, node: &Node, pos: usize, k: &mut dyn FnMut(usize) -> bool) -> bool {
+        match node {
+            Node::Atom(atom) => self.line[pos..]
+                .chars()
+                .next()
+                .filter(|&c| self.matches(atom, c))
+                .is_some_and(|c| k(pos + c.len_utf8())),
+            Node::Start => pos == 0 && self.line_start && k(pos),
+            Node::End => pos == self.line.len() && k(pos),
+            Node::Boundary => {
+                let word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+                let before = word(self.line[..pos].chars().next_back());
+                let after = word(self.line[pos..].chars().next());
+                before != after && k(pos)
+            }
+            Node::Group(alternatives) => alternatives.iter().any(|seq| self.seq(seq, pos, k)),
+            Node::Repeat(..) => self.seq(std::slice::from_ref(node), pos, k),
+        }
+    }
+
+    fn repeat(
+        &self,
+        inner: &Node,
+        min: usize,
+        max: usize,
+        rest: &[Node],
+        pos: usize,
+        k: &mut dyn FnMut(usize) -> bool,
+    ) -> bool {
+        if let Node::Atom(atom) = inner {
+            // Greedy ends of repeated chars, tried longest first without recursing per char.
+            let mut ends = vec![pos];
+            for c in self.line[pos..].chars().take(max) {
+                if !self.matches(atom, c) {
+                    break;
+                }
+                ends.push(ends[ends.len() - 1] + c.len_utf8());
+            }
+            return ends
+                .iter()
+                .skip(min)
+                .rev()
+                .any(|&end| self.step() && self.seq(rest, end, k));
+        }
+        if max > 0
+            && self.one(inner, pos, &mut |end| {
+                end > pos && self.repeat(inner, min.saturating_sub(1), max - 1, rest, end, k)
+            })
+        {
+            return true;
+        }
+        min == 0 && self.seq(rest, pos, k)
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &'static str) -> PatternError {
+        PatternError {
+            position: self.position,
+            message,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.position += c.is_some() as usize;
+        c
+    }
+
+    fn alternatives(&mut self) -> Result<Vec<Vec<Node>>, PatternError> {
+        let mut alternatives = vec![self.seq()?];
+        while self.peek() == Some('|') {
+            self.position += 1;
+            alternatives.push(self.seq()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn seq(&mut self) -> Result<Vec<Node>, PatternError> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek()
+            && c != '|'
+            && c != ')'
+        {
+            let node = self.atom()?;
+            nodes.push(self.quantifier(node)?);
+        }
+        Ok(nodes)
+    }
+
+    fn atom(&mut self) -> Result<Node, PatternError> {
+        Ok(match self.next() {
+            Some('(') => {
+                let alternatives = self.alternatives()?;
+                if self.next() != Some(')') {
+                    return Err(self.error("unclosed `(`"));
+                }
+                Node::Group(alternatives)
+            }
+            Some('[') => Node::Atom(self.class()?),
+            Some('.') => Node::Atom(Atom::Any),
+            Some('^') => Node::Start,
+            Some('$') => Node::End,
+            Some('\\') if self.peek() == Some('b') => {
+                self.position += 1;
+                Node::Boundary
+            }
+            Some('\\') => Node::Atom(self.escape()?),
+            Some('*' | '+' | '?' | '{') => return Err(self.error("nothing to repeat")),
+            Some(c) => Node::Atom(Atom::Char(c)),
+            None => return Err(self.error("unexpected end")),
+        })
+    }
+
+    fn escape(&mut self) -> Result<Atom, PatternError> {
+        Ok(match self.next() {
+            Some('d') => Atom::Digit(false),
+            Some('D') => Atom::Digit(true),
+            Some('w') => Atom::Word(false),
+            Some('W') => Atom::Word(true),
+            Some('s') => Atom::Space(false),
+            Some('S') => Atom::Space(true),
+            Some('t') => Atom::Char('\t'),
+            Some('n') => Atom::Char('\n'),
+            Some(c) if c.is_alphanumeric() => return Err(self.error("unsupported escape")),
+            Some(c) => Atom::Char(c),
+            None => return Err(self.error("unfinished escape")),
+        })
+    }
+
+    fn class(&mut self) -> Result<Atom, PatternError> {
+        let negated = self.peek() == Some('^');
+        self.position += negated as usize;
+        let mut atoms = Vec::new();
+        loop {
+            let atom = match self.next() {
+                Some(']') if !atoms.is_empty() => return Ok(Atom::Class(negated, atoms)),
+                Some('\\') => self.escape()?,
+                Some('[') if self.peek() == Some(':') => {
+                    return Err(self.error("unsupported class"));
+                }
+                Some(c) => Atom::Char(c),
+                None => return Err(self.error("unclosed `[`")),
+            };
+            match (atom, self.peek(), self.chars.get(self.position + 1)) {
+                (Atom::Char(a), Some('-'), Some(&b)) if b != ']' => {
+                    self.position += 2;
+                    if b < a {
+                        return Err(self.error("invalid range"));
+                    }
+                    atoms.push(Atom::Range(a, b));
+                }
+                (atom, _, _) => atoms.push(atom),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        self.chars[start..self.position]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+
+    fn quantifier(&mut self, node: Node) -> Result<Node, PatternError> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, usize::MAX),
+            Some('+') => (1, usize::MAX),
+            Some('?') => (0, 1),
+            Some('{') => {
+                self.position += 1;
+                let min = self.number().ok_or_else(|| self.error("expected number"))?;
+                let max = match self.next() {
+                    Some('}') => min,
+                    Some(',') if self.peek() == Some('}') => {
+                        self.position += 1;
+                        usize::MAX
+                    }
+                    Some(',') => {
+                        let max = self.number().ok_or_else(|| self.error("expected number"))?;
+                        if self.next() != Some('}') || max < min {
+                            return Err(self.error("invalid repetition"));
+                        }
+                        max
+                    }
+                    _ => return Err(self.error("unclosed `{`")),
+                };
+                return Ok(Node::Repeat(Box::new(node), min, max));
+            }
+            _ => return Ok(node),
+        };
+        self.position += 1;
+        Ok(Node::Repeat(Box::new(node), min, max))
+    }
+}
@@ -1,28 +1,73 @@
 use crate::syntax::Patch;
 
-use super::{DEFAULT_QUOTES, Syntax};
+use super::{
+    DEFAULT_QUOTES, Escape, HEX_DIGITS, Interpolation, Numbers, OCT_DIGITS, PRINTF_CONVERSIONS,
+    PRINTF_FLAGS, StringDelimiter, Syntax, pairs, words,
+};
 use std::collections::BTreeSet;
 
+const PREFIXES: [&str; 15] = [
+    "", "r", "u", "R", "U", "b", "B", "br", "Br", "bR", "BR", "rb", "rB", "Rb", "RB",
+];
+const F_PREFIXES: [&str; 10] = ["f", "F", "fr", "Fr", "fR", "FR", "rf", "rF", "Rf", "RF"];
+
 impl Syntax {
     pub fn python() -> Syntax {
         Syntax {
-            language: "Python",
+            language: "Python".into(),
             case_sensitive: true,
-            comment: "#",
-            comment_multiline: [r#"'''"#, r#"'''"#],
+            comment: "#".into(),
+            comment_multiline: ["".into(), "".into()],
+            nested_comments: false,
+            alt_comments: BTreeSet::new(),
+            alt_comments_multiline: BTreeSet::new(),
             quotes: DEFAULT_QUOTES.into(),
+            strings: ["\"", "'", "\"\"\"", "'''"]
+                .into_iter()
+                .flat_map(|quote| {
+                    [
+                        StringDelimiter::new(quote, quote).with_prefixes(PREFIXES),
+                        StringDelimiter::new(quote, quote)
+                            .with_prefixes(F_PREFIXES)
+                            .with_interpolations([Interpolation::new("{", "}")]),
+                    ]
+                })
+                .collect(),
+            escapes: BTreeSet::from([
+                Escape::new("\\").with_end("\\'\"abfnrtv"),
+                Escape::new("\\").with_chars(OCT_DIGITS, 1, 3),
+                Escape::new("\\x").with_chars(HEX_DIGITS, 2, 2),
+                Escape::new("\\u").with_chars(HEX_DIGITS, 4, 4),
+                Escape::new("\\U").with_chars(HEX_DIGITS, 8, 8),
+                Escape::new("%")
+                    .with_chars(PRINTF_FLAGS, 0, 8)
+                    .with_end(PRINTF_CONVERSIONS),
+            ]),
+            numbers: Numbers::default().with_prefixes(Numbers::RADIX_PREFIXES),
+            operators: words([
+                "!=", "%=", "&=", "**", "**=", "*=", "+=", "-=", "->", "//", "//=", "/=", ":=",
+                "<<", "<<=", "<=", "==", ">=", ">>", ">>=", "@=", "^=", "|=",
+            ]),
+            lifetime: None,
+            attributes: BTreeSet::new(),
+            macro_suffix: None,
+            patterns: Vec::new(),
+            injections: Vec::new(),
+            fold_markers: pairs([["#region", "#endregion"]]),
+            indent_after: words(["{", "(", "[", ":"]),
+            dedent_before: words(["}", ")", "]", "else", "elif", "except", "finally"]),
             word_start: BTreeSet::from(['_']),
-            hyperlinks: BTreeSet::from([
+            hyperlinks: words([
                 "http:", "HTTP:", "https:", "HTTPS:", "www.", "WWW.", "ftp:", "FTP:", "file:",
                 "FILE:",
             ]),
-            keywords: BTreeSet::from([
+            keywords: words([
                 "and", "as", "assert", "break", "class", "continue", "def", "del", "elif", "else",
                 "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda",
                 "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with",
                 "yield",
             ]),
-            types: BTreeSet::from([
+            types: words([
                 "bool",
                 "int",
                 "float",
@@ -38,7 +83,7 @@ impl Syntax {
                 "set",
                 "frozenset",
             ]),
-            special: BTreeSet::from(["False", "None", "True"]),
+            special: words(["False", "None", "True"]),
             patch: Patch::default(),
         }
     }
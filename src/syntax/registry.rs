@@ -0,0 +1,178 @@
+use super::Syntax;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq)]
+/// Syntax with the ways to recognize its files.
+pub struct SyntaxEntry {
+    pub syntax: Syntax,
+    /// File extensions without dot, compared ignoring case.
+    pub extensions: BTreeSet<Cow<'static, str>>,
+    /// Interpreters in `#!` lines, compared ignoring version, like `python3.12`.
+    pub interpreters: BTreeSet<Cow<'static, str>>,
+    /// Snippets typical for the language, counted to guess it from content.
+    pub hints: BTreeSet<Cow<'static, str>>,
+}
+
+impl SyntaxEntry {
+    pub fn new(syntax: Syntax) -> Self {
+        SyntaxEntry {
+            syntax,
+            extensions: BTreeSet::new(),
+            interpreters: BTreeSet::new(),
+            hints: BTreeSet::new(),
+        }
+    }
+    pub fn with_extensions<T: IntoIterator<Item = S>, S: Into<Cow<'static, str>>>(
+        self,
+        extensions: T,
+    ) -> Self {
+        SyntaxEntry {
+            extensions: extensions.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+    pub fn with_interpreters<T: IntoIterator<Item = S>, S: Into<Cow<'static, str>>>(
+        self,
+        interpreters: T,
+    ) -> Self {
+        SyntaxEntry {
+            interpreters: interpreters.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+    pub fn with_hints<T: IntoIterator<Item = S>, S: Into<Cow<'static, str>>>(
+        self,
+        hints: T,
+    ) -> Self {
+        SyntaxEntry {
+            hints: hints.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Known syntaxes, looked up by language name, file extension, shebang or content.
+///
+/// Entries registered later take precedence.
+pub struct SyntaxRegistry {
+    entries: Vec<SyntaxEntry>,
+}
+
+impl Default for SyntaxRegistry {
+    /// Registry of the built-in syntaxes.
+    fn default() -> Self {
+        let mut registry = SyntaxRegistry::new();
+        registry.register(
+            SyntaxEntry::new(Syntax::asm())
+                .with_extensions(["asm", "s", "nasm"])
+                .with_hints(["mov ", "eax", "section .", "jmp ", "db "]),
+        );
+        registry.register(
+            SyntaxEntry::new(Syntax::sql())
+                .with_extensions(["sql"])
+                .with_hints(["SELECT ", "FROM ", "WHERE ", "CREATE TABLE", "INSERT INTO"]),
+        );
+        registry.register(
+            SyntaxEntry::new(Syntax::shell())
+                .with_extensions(["sh", "bash", "zsh", "ksh"])
+                .with_interpreters(["sh", "bash", "zsh", "ksh", "dash"])
+                .with_hints(["echo ", "fi\n", "esac", "$(", "then\n", "done\n"]),
+        );
+        registry.register(
+            SyntaxEntry::new(Syntax::lua())
+                .with_extensions(["lua"])
+                .with_interpreters(["lua", "luajit"])
+                .with_hints(["local ", "function ", "end\n", " then\n", "~=", "--"]),
+        );
+        registry.register(
+            SyntaxEntry::new(Syntax::python())
+                .with_extensions(["py", "pyw", "pyi"])
+                .with_interpreters(["python"])
+                .with_hints(["def ", "import ", "self.", "elif ", "__init__", "):\n"]),
+        );
+        registry.register(
+            SyntaxEntry::new(Syntax::rust())
+                .with_extensions(["rs"])
+                .with_hints(["fn ", "let ", "::", "->", "impl ", "pub ", "#["]),
+        );
+        registry
+    }
+}
+
+impl SyntaxRegistry {
+    /// Empty registry.
+    pub fn new() -> Self {
+        SyntaxRegistry {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, entry: SyntaxEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &SyntaxEntry> {
+        self.entries.iter().rev()
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&Syntax> {
+        self.entries()
+            .map(|e| &e.syntax)
+            .find(|s| s.language().eq_ignore_ascii_case(name))
+    }
+
+    /// Extension without dot, like `rs`.
+    pub fn by_extension(&self, extension: &str) -> Option<&Syntax> {
+        self.entries()
+            .find(|e| {
+                e.extensions
+                    .iter()
+                    .any(|x| x.eq_ignore_ascii_case(extension))
+            })
+            .map(|e| &e.syntax)
+    }
+
+    /// Interpreter of the `#!` line, like `#!/usr/bin/env python3`.
+    pub fn by_shebang(&self, line: &str) -> Option<&Syntax> {
+        let mut words = line.strip_prefix("#!")?.split_whitespace();
+        let mut interpreter = words.next()?.rsplit('/').next()?;
+        if interpreter == "env" {
+            interpreter = words.find(|w| !w.starts_with('-'))?;
+        }
+        let interpreter = unversioned(interpreter);
+        self.entries()
+            .find(|e| e.interpreters.iter().any(|i| unversioned(i) == interpreter))
+            .map(|e| &e.syntax)
+    }
+
+    /// Syntax with the most hints found in `text`.
+    pub fn guess(&self, text: &str) -> Option<&Syntax> {
+        self.entries()
+            .map(|e| {
+                (
+                    e.hints.iter().filter(|h| text.contains(h.as_ref())).count(),
+                    e,
+                )
+            })
+            .filter(|(hits, _)| *hits > 0)
+            .reduce(|best, e| if e.0 > best.0 { e } else { best })
+            .map(|(_, e)| &e.syntax)
+    }
+
+    /// Syntax by file extension, then by shebang, then by content.
+    pub fn detect<P: AsRef<Path>>(&self, path: P, text: &str) -> Option<&Syntax> {
+        let extension = path.as_ref().extension().and_then(|e| e.to_str());
+        extension
+            .and_then(|e| self.by_extension(e))
+            .or_else(|| self.by_shebang(text.lines().next().unwrap_or_default()))
+            .or_else(|| self.guess(text))
+    }
+}
+
+/// Interpreter name without version, like `python` for `python3.12`.
+fn unversioned(name: &str) -> &str {
+    name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.')
+}
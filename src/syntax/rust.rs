@@ -1,29 +1,59 @@
 use crate::syntax::Patch;
 
-use super::{DEFAULT_QUOTES, Syntax};
+use super::{DEFAULT_QUOTES, Escape, HEX_DIGITS, Numbers, StringDelimiter, Syntax, pairs, words};
 use std::collections::BTreeSet;
 
 impl Syntax {
     pub fn rust() -> Self {
         Syntax {
-            language: "Rust",
+            language: "Rust".into(),
             case_sensitive: true,
-            comment: "//",
-            comment_multiline: ["/*", "*/"],
+            comment: "//".into(),
+            comment_multiline: ["/*".into(), "*/".into()],
+            nested_comments: true,
+            alt_comments: BTreeSet::new(),
+            alt_comments_multiline: BTreeSet::new(),
             quotes: DEFAULT_QUOTES.into(),
+            strings: BTreeSet::from([
+                StringDelimiter::new("\"", "\"").with_prefixes(["", "b", "c"]),
+                StringDelimiter::new("#\"", "\"#")
+                    .with_prefixes(["r", "br", "cr"])
+                    .with_repeat('#')
+                    .with_escape(None),
+            ]),
+            escapes: BTreeSet::from([
+                Escape::new("\\").with_end("nrt0\\'\""),
+                Escape::new("\\x").with_chars(HEX_DIGITS, 2, 2),
+                Escape::new("\\u{")
+                    .with_chars(HEX_DIGITS, 1, 6)
+                    .with_end("}"),
+            ]),
+            numbers: Numbers::default().with_prefixes(Numbers::RADIX_PREFIXES),
+            operators: words([
+                "!=", "%=", "&&", "&=", "*=", "+=", "-=", "->", "..", "...", "..=", "/=", "::",
+                "<<", "<<=", "<=", "==", "=>", ">=", ">>", ">>=", "^=", "|=", "||",
+            ]),
+            lifetime: Some('\''),
+            attributes: words(["#[", "#!["]),
+            macro_suffix: Some('!'),
+            patterns: Vec::new(),
+            injections: Vec::new(),
+            fold_markers: pairs([["#region", "#endregion"]]),
+            indent_after: words(["{", "(", "["]),
+            dedent_before: words(["}", ")", "]"]),
             word_start: BTreeSet::from(['_']),
-            hyperlinks: BTreeSet::from([
+            hyperlinks: words([
                 "http:", "HTTP:", "https:", "HTTPS:", "www.", "WWW.", "ftp:", "FTP:", "file:",
                 "FILE:",
             ]),
-            keywords: BTreeSet::from([
+            keywords: words([
                 "as", "break", "const", "continue", "crate", "else", "enum", "extern", "fn", "for",
                 "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
                 "return", "self", "struct", "super", "trait", "type", "use", "where", "while",
                 "async", "await", "abstract", "become", "box", "do", "final", "macro", "override",
                 "priv", "typeof", "unsized", "virtual", "yield", "try", "unsafe", "dyn",
             ]),
-            types: BTreeSet::from([
+            types: words([
                 "Option",
                 "Result",
                 "Error",
@@ -89,7 +119,7 @@ impl Syntax {
                 "WaitTimeoutResult",
                 "Weak",
             ]),
-            special: BTreeSet::from(["Self", "static", "true", "false"]),
+            special: words(["Self", "static", "true", "false"]),
             patch: Patch::default(),
         }
     }
@@ -1,27 +1,61 @@
 use crate::syntax::Patch;
 
-use super::{DEFAULT_QUOTES, Syntax};
+use super::{
+    DEFAULT_QUOTES, Escape, HEX_DIGITS, Interpolation, Numbers, PRINTF_CONVERSIONS, PRINTF_FLAGS,
+    StringDelimiter, Syntax, pairs, words,
+};
 use std::collections::BTreeSet;
 
 impl Syntax {
     pub fn shell() -> Self {
         Syntax {
-            language: "Shell",
+            language: "Shell".into(),
             case_sensitive: true,
-            comment: "#",
+            comment: "#".into(),
             quotes: DEFAULT_QUOTES.into(),
+            strings: BTreeSet::from([
+                StringDelimiter::new("'", "'").with_escape(None),
+                StringDelimiter::new("\"", "\"").with_interpolations([
+                    Interpolation::new("${", "}"),
+                    Interpolation::new("$(", ")"),
+                    Interpolation::new("$", ""),
+                ]),
+                StringDelimiter::heredoc("<<"),
+                StringDelimiter::heredoc("<<-"),
+            ]),
+            escapes: BTreeSet::from([
+                Escape::new("\\").with_end("\\\"$`"),
+                Escape::new("%")
+                    .with_chars(PRINTF_FLAGS, 0, 8)
+                    .with_end(PRINTF_CONVERSIONS),
+            ]),
+            numbers: Numbers::default().with_prefixes([("0x", HEX_DIGITS), ("0X", HEX_DIGITS)]),
+            operators: words([
+                "!=", "&&", "&>", "+=", ";;", "<&", "<<", "==", ">&", ">>", "||",
+            ]),
+            lifetime: None,
+            attributes: BTreeSet::new(),
+            macro_suffix: None,
+            patterns: Vec::new(),
+            injections: Vec::new(),
+            fold_markers: pairs([["#region", "#endregion"]]),
+            indent_after: words(["{", "(", "then", "do", "else"]),
+            dedent_before: words(["}", ")", "fi", "done", "else", "elif", "esac"]),
             word_start: BTreeSet::from(['_']),
-            hyperlinks: BTreeSet::from([
+            hyperlinks: words([
                 "http:", "HTTP:", "https:", "HTTPS:", "www.", "WWW.", "ftp:", "FTP:", "file:",
                 "FILE:",
             ]),
-            keywords: BTreeSet::from([
+            keywords: words([
                 "echo", "read", "set", "unset", "readonly", "shift", "export", "if", "fi", "else",
                 "while", "do", "done", "for", "until", "case", "esac", "break", "continue", "exit",
                 "return", "trap", "wait", "eval", "exec", "ulimit", "umask",
             ]),
-            comment_multiline: [": '", "'"],
-            types: BTreeSet::from([
+            comment_multiline: [": '".into(), "'".into()],
+            nested_comments: false,
+            alt_comments: BTreeSet::new(),
+            alt_comments_multiline: BTreeSet::new(),
+            types: words([
                 "ENV",
                 "HOME",
                 "IFS",
@@ -39,7 +73,7 @@ impl Syntax {
                 "PS4",
                 "PWD",
             ]),
-            special: BTreeSet::from([
+            special: words([
                 "alias", "bg", "cd", "command", "false", "fc", "fg", "getopts", "jobs", "kill",
                 "newgrp", "pwd", "read", "true", "umask", "unalias", "wait",
             ]),
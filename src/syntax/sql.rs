@@ -1,19 +1,34 @@
 use crate::syntax::Patch;
 
-use super::{DEFAULT_QUOTES, Syntax};
+use super::{DEFAULT_QUOTES, Numbers, Syntax, pairs, words};
 use std::collections::BTreeSet;
 
 impl Syntax {
     pub fn sql() -> Self {
         Syntax {
-            language: "SQL",
+            language: "SQL".into(),
             case_sensitive: false,
-            comment: "--",
-            comment_multiline: ["/*", "*/"],
+            comment: "--".into(),
+            comment_multiline: ["/*".into(), "*/".into()],
+            nested_comments: false,
+            alt_comments: words(["#"]),
+            alt_comments_multiline: BTreeSet::new(),
             quotes: DEFAULT_QUOTES.into(),
+            strings: BTreeSet::new(),
+            escapes: BTreeSet::new(),
+            numbers: Numbers::default(),
+            operators: words(["!=", "::", "<=", "<>", ">=", "||"]),
+            lifetime: None,
+            attributes: BTreeSet::new(),
+            macro_suffix: None,
+            patterns: Vec::new(),
+            injections: Vec::new(),
+            fold_markers: pairs([["#region", "#endregion"]]),
+            indent_after: words(["("]),
+            dedent_before: words([")"]),
             word_start: BTreeSet::from(['_']),
-            hyperlinks: BTreeSet::from(["http:", "https:", "www.", "ftp:", "file:"]),
-            keywords: BTreeSet::from([
+            hyperlinks: words(["http:", "https:", "www.", "ftp:", "file:"]),
+            keywords: words([
                 "ADD",
                 "ALL",
                 "ALTER",
@@ -75,7 +90,7 @@ impl Syntax {
                 "WHERE",
                 "WITH",
             ]),
-            types: BTreeSet::from([
+            types: words([
                 "BOOL",
                 "INTEGER",
                 "SMALLINT",
@@ -136,7 +151,7 @@ impl Syntax {
                 "SET",
                 "DATABASE",
             ]),
-            special: BTreeSet::from(["PUBLIC"]),
+            special: words(["PUBLIC"]),
             patch: Patch::default(),
         }
     }
@@ -0,0 +1,303 @@
+//! Importing TextMate grammars (`.tmLanguage.json`, also used by Sublime Text and VS Code).
+//!
+//! Rules are read from `patterns` and `repository`, and converted by their scope names:
+//! - `match` rules listing words, like `\b(if|else)\b`, fill keywords, types and special words;
+//! - `comment.line` and `comment.block` rules with literal markers become comment markers;
+//! - `string` rules with literal `begin` and `end` become string delimiters, with a backslash
+//!   escape if their patterns have a `constant.character.escape` rule, directly or included;
+//! - other `match` rules with a known scope become [`Syntax::patterns`] if [`Pattern`] supports them.
+//!
+//! Constructs the lexer can't express, like nested scopes, captures and lookarounds, are skipped.
+//!
+//! Grammars are converted, not run: there is no scope-based tokenizer.
+
+use super::loader::LoadError;
+use super::pattern::Pattern;
+use super::{StringDelimiter, Syntax, TokenType};
+use serde_json::{Map, Value};
+
+/// Token type for a TextMate scope name, most specific first.
+fn scope_type(scope: &str) -> Option<TokenType> {
+    const SCOPES: [(&str, TokenType); 14] = [
+        ("comment", TokenType::Comment(false)),
+        ("string", TokenType::Str('"')),
+        ("constant.numeric", TokenType::Numeric(false)),
+        ("constant.character.escape", TokenType::Escape),
+        ("constant.language", TokenType::Special),
+        ("variable.language", TokenType::Special),
+        ("support.constant", TokenType::Special),
+        ("keyword.operator", TokenType::Operator),
+        ("keyword", TokenType::Keyword),
+        ("storage.type", TokenType::Type),
+        ("storage", TokenType::Keyword),
+        ("support.type", TokenType::Type),
+        ("entity.name.type", TokenType::Type),
+        ("entity.name.function", TokenType::Function),
+    ];
+    SCOPES
+        .iter()
+        .find(|(prefix, _)| {
+            scope.starts_with(prefix)
+                && scope[prefix.len()..]
+                    .chars()
+                    .next()
+                    .is_none_or(|c| c == '.')
+        })
+        .map(|(_, ty)| *ty)
+}
+
+/// Regex matching exactly one literal text.
+fn literal(regex: &str) -> Option<String> {
+    let mut text = String::new();
+    let mut chars = regex.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                c if c.is_alphanumeric() => return None,
+                c => text.push(c),
+            },
+            '.' | '*' | '+' | '?' | '[' | ']' | '(' | ')' | '{' | '}' | '|' | '^' | '$' => {
+                return None;
+            }
+            c => text.push(c),
+        }
+    }
+    (!text.is_empty()).then_some(text)
+}
+
+/// Literal text at the start of a regex, like `//` in `(//).*$`.
+fn literal_prefix(regex: &str) -> Option<String> {
+    let regex = regex.trim_start_matches('^');
+    let regex = regex
+        .strip_prefix("(?:")
+        .or_else(|| regex.strip_prefix('('))
+        .unwrap_or(regex);
+    let mut text = String::new();
+    let mut chars = regex.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c) if !c.is_alphanumeric() => text.push(c),
+                _ => break,
+            },
+            '.' | '*' | '+' | '?' | '[' | ']' | '(' | ')' | '{' | '}' | '|' | '^' | '$' => break,
+            c => text.push(c),
+        }
+    }
+    (!text.is_empty()).then_some(text)
+}
+
+/// Alternatives of a regex listing literal texts, like `\b(if|else)\b` or `==|!=`.
+fn alternatives(regex: &str) -> Option<Vec<String>> {
+    let mut regex = regex;
+    for affix in ["\\b", "(?:", "(", "\\b"] {
+        regex = regex.strip_prefix(affix).unwrap_or(regex);
+    }
+    for affix in ["\\b", ")", "\\b"] {
+        regex = regex.strip_suffix(affix).unwrap_or(regex);
+    }
+    let mut parts = vec![String::new()];
+    let mut escaped = false;
+    for c in regex.chars() {
+        match c {
+            '|' if !escaped => parts.push(String::new()),
+            _ => {
+                if let Some(part) = parts.last_mut() {
+                    part.push(c);
+                }
+            }
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    parts.iter().map(|part| literal(part)).collect()
+}
+
+/// Whether `pattern` matches escapes: a rule named by a `constant.character.escape` scope,
+/// or a group or `#name` include of the grammar `repository` containing one.
+fn is_escape(
+    pattern: &Map<String, Value>,
+    repository: Option<&Map<String, Value>>,
+    depth: usize,
+) -> bool {
+    let named = ["name", "scope"]
+        .into_iter()
+        .filter_map(|key| pattern.get(key)?.as_str())
+        .flat_map(str::split_whitespace)
+        .any(|scope| scope_type(scope) == Some(TokenType::Escape));
+    let included = || {
+        let name = pattern.get("include")?.as_str()?.strip_prefix('#')?;
+        repository?.get(name)?.as_object()
+    };
+    let patterns = || match pattern.get("patterns") {
+        Some(Value::Array(patterns)) => patterns.as_slice(),
+        _ => &[],
+    };
+    named
+        || depth > 0
+            && (included().is_some_and(|rule| is_escape(rule, repository, depth - 1))
+                || patterns().iter().any(|rule| {
+                    rule.as_object()
+                        .is_some_and(|rule| is_escape(rule, repository, depth - 1))
+                }))
+}
+
+fn field<'a>(
+    rule: &'a Map<String, Value>,
+    key: &str,
+    path: &str,
+) -> Result<Option<&'a str>, LoadError> {
+    match rule.get(key) {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(LoadError::Grammar(format!(
+            "`{path}.{key}` is not a string"
+        ))),
+    }
+}
+
+/// All rules with their JSON paths, in grammar order.
+fn rules<'a>(
+    value: &'a Value,
+    path: String,
+    found: &mut Vec<(String, &'a Map<String, Value>)>,
+) -> Result<(), LoadError> {
+    let Value::Object(rule) = value else {
+        return Err(LoadError::Grammar(format!("`{path}` is not an object")));
+    };
+    found.push((path.clone(), rule));
+    match rule.get("patterns") {
+        None => (),
+        Some(Value::Array(patterns)) => {
+            for (i, pattern) in patterns.iter().enumerate() {
+                rules(pattern, format!("{path}.patterns[{i}]"), found)?;
+            }
+        }
+        Some(_) => {
+            return Err(LoadError::Grammar(format!(
+                "`{path}.patterns` is not an array"
+            )));
+        }
+    }
+    match rule.get("repository") {
+        None => Ok(()),
+        Some(Value::Object(repository)) => repository
+            .iter()
+            .try_for_each(|(name, rule)| rules(rule, format!("{path}.repository.{name}"), found)),
+        Some(_) => Err(LoadError::Grammar(format!(
+            "`{path}.repository` is not an object"
+        ))),
+    }
+}
+
+impl Syntax {
+    /// Syntax from a TextMate grammar in JSON.
+    pub fn from_textmate(json: &str) -> Result<Self, LoadError> {
+        let grammar: Value = serde_json::from_str(json).map_err(LoadError::Json)?;
+        let Value::Object(root) = &grammar else {
+            return Err(LoadError::Grammar(
+                "grammar is not a JSON object".to_string(),
+            ));
+        };
+        if !matches!(root.get("patterns"), Some(Value::Array(_))) {
+            return Err(LoadError::Grammar(
+                "grammar has no `patterns` array".to_string(),
+            ));
+        }
+        let language = match (
+            field(root, "name", "grammar")?,
+            field(root, "scopeName", "grammar")?,
+        ) {
+            (Some(name), _) => name.to_string(),
+            (None, Some(scope)) => scope.trim_start_matches("source.").to_string(),
+            (None, None) => "TextMate".to_string(),
+        };
+        let repository = root.get("repository").and_then(Value::as_object);
+        let mut found = Vec::new();
+        rules(&grammar, "grammar".to_string(), &mut found)?;
+
+        let mut syntax = Syntax::new(language)
+            .with_comment("")
+            .with_comment_multiline(["", ""]);
+        let (mut comments, mut comments_multiline) = (Vec::new(), Vec::new());
+        for (path, rule) in found {
+            let Some(scope) = field(rule, "name", &path)? else {
+                continue;
+            };
+            let Some(ty) = scope.split_whitespace().find_map(scope_type) else {
+                continue;
+            };
+            let regex = field(rule, "match", &path)?;
+            let begin = field(rule, "begin", &path)?;
+            let end = field(rule, "end", &path)?;
+            match ty {
+                TokenType::Comment(_) => {
+                    let open = regex.or(begin).and_then(literal_prefix);
+                    let close = end.and_then(literal);
+                    match (open, close) {
+                        (Some(open), Some(close)) if scope.starts_with("comment.block") => {
+                            comments_multiline.push([open, close]);
+                        }
+                        (Some(open), _) if !scope.starts_with("comment.block") => {
+                            comments.push(open);
+                        }
+                        _ => (),
+                    }
+                }
+                TokenType::Str(_) => {
+                    if let Some(open) = begin.and_then(literal)
+                        && let Some(close) = end.and_then(literal)
+                    {
+                        let escapes = is_escape(rule, repository, 8);
+                        let delimiter = StringDelimiter::new(open, close);
+                        syntax.strings.insert(if escapes {
+                            delimiter
+                        } else {
+                            delimiter.with_escape(None)
+                        });
+                    }
+                }
+                ty => {
+                    let Some(regex) = regex else {
+                        continue;
+                    };
+                    let words = alternatives(regex);
+                    match (ty, words) {
+                        (TokenType::Keyword, Some(words)) => {
+                            syntax.keywords.extend(words.into_iter().map(Into::into))
+                        }
+                        (TokenType::Type, Some(words)) => {
+                            syntax.types.extend(words.into_iter().map(Into::into))
+                        }
+                        (TokenType::Special, Some(words)) => {
+                            syntax.special.extend(words.into_iter().map(Into::into))
+                        }
+                        (TokenType::Operator, Some(words)) => {
+                            syntax.operators.extend(words.into_iter().map(Into::into))
+                        }
+                        (ty, _) => {
+                            if let Ok(pattern) = Pattern::new(regex.to_string()) {
+                                syntax.patterns.push((pattern, ty));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if !syntax.strings.is_empty() {
+            syntax.quotes.clear();
+        }
+        let mut comments = comments.into_iter();
+        if let Some(comment) = comments.next() {
+            syntax = syntax.with_comment(comment);
+        }
+        syntax.alt_comments.extend(comments.map(Into::into));
+        let mut comments_multiline = comments_multiline.into_iter();
+        if let Some(comment_multiline) = comments_multiline.next() {
+            syntax = syntax.with_comment_multiline(comment_multiline);
+        }
+        syntax
+            .alt_comments_multiline
+            .extend(comments_multiline.map(|pair| pair.map(Into::into)));
+        Ok(syntax)
+    }
+}
@@ -29,11 +29,7 @@ fn qustom_quotes() {
 fn numeric_float_desription() {
     assert_eq!(
         Token::default().tokens(&Syntax::default(), "3.14_f32"),
-        [
-            Token::new(TokenType::Numeric(true), "3.14"),
-            Token::new(TokenType::Numeric(true), "_"),
-            Token::new(TokenType::Numeric(true), "f32")
-        ]
+        [Token::new(TokenType::Numeric(true), "3.14_f32")]
     );
 }
 
@@ -80,3 +76,1166 @@ fn simple_rust() {
     println!("{str}");
     assert_eq!(input, output);
 }
+
+#[test]
+fn incremental_relex() {
+    use crate::highlighting::Highlighter;
+
+    let syntax = Syntax::rust();
+    let edits = [
+        "fn main() {\n    let x = 1;\n}\n// end\n",
+        "fn main() {\n    let x = 12;\n}\n// end\n",
+        "fn main() {\n /* let x = 12;\n}\n// end\n",
+        "fn main() {\n /* let x = 12;\n}*/\n// end\n",
+        "fn main() {\n /* let x = 12;\n}*/\n\"// end\n",
+        "fn main() {\n    let x = \"12;\n}\n// end\n",
+        "",
+        "fn\n",
+    ];
+    let mut highlighter = Highlighter::default();
+    for text in edits {
+        assert_eq!(
+            highlighter.update(&syntax, text),
+            syntax.spans(text).collect::<Vec<_>>(),
+            "{text:?}"
+        );
+    }
+
+    let python = Syntax::python();
+    let edits = [
+        "x = 1\ns = f\"\"\"a\n{x}\n{y:>{w}}\nb\"\"\"\nz = 2\n",
+        "x = 12\ns = f\"\"\"a\n{x}\n{y:>{w}}\nb\"\"\"\nz = 2\n",
+        "\nx = 12\ns = f\"\"\"a\n{x}\n{y:>{w}}\nb\"\"\"\nz = 2\n",
+        "x = 2\ns = f\"\"\"a\n{x}\n{y:>{w}}\nb\"\"\"\nz = 2\n",
+    ];
+    let mut highlighter = Highlighter::default();
+    for text in edits {
+        assert_eq!(
+            highlighter.update(&python, text),
+            python.spans(text).collect::<Vec<_>>(),
+            "{text:?}"
+        );
+    }
+}
+
+#[test]
+fn incremental_relex_random() {
+    use crate::Pattern;
+    use crate::highlighting::Highlighter;
+
+    let pieces = [
+        "\n", " ", "\t", "x", "é", "€", "1", "0x", "1e", "'", "\"", "\\", "#", "#[", "]", "/*",
+        "*/", "//", "--", "<<", "EOF", "{", "}", "$", "f\"", "\"\"\"", "'a", "!", "=", "r#\"",
+        "[[", "]]", ";", "..", "fn", "let", "@", "'\\n'", "\n\n", "f\"{", "f'''{", "${", "}\n",
+        "{x:", "'''",
+    ];
+    let mut seed = 0x2545_f491_u64;
+    let mut next = |n: usize| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed as usize % n
+    };
+    let pattern = Pattern::new(r"x\s+1|@[^@]*@").unwrap();
+    for syntax in [
+        Syntax::rust().with_patterns([(pattern, TokenType::Special)]),
+        Syntax::rust(),
+        Syntax::python(),
+        Syntax::shell(),
+        Syntax::lua(),
+        Syntax::sql(),
+        Syntax::asm(),
+    ] {
+        for _ in 0..100 {
+            let mut highlighter = Highlighter::default();
+            let mut text = String::new();
+            for _ in 0..30 {
+                let chars: Vec<usize> = text
+                    .char_indices()
+                    .map(|(i, _)| i)
+                    .chain([text.len()])
+                    .collect();
+                let start = chars[next(chars.len())];
+                let end = chars[(chars.iter().position(|&i| i == start).unwrap_or(0) + next(4))
+                    .min(chars.len() - 1)];
+                let insert: String = (0..next(5)).map(|_| pieces[next(pieces.len())]).collect();
+                text.replace_range(start..end, &insert);
+                assert_eq!(
+                    highlighter.update(&syntax, &text),
+                    syntax.spans(&text).collect::<Vec<_>>(),
+                    "{} {text:?}",
+                    syntax.language()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn spans_ranges() {
+    let text = "é = \"ü\";";
+    let spans = Syntax::rust().spans(text).collect::<Vec<_>>();
+    assert_eq!(
+        spans,
+        [
+            Span {
+                ty: TokenType::Literal,
+                bytes: 0..2,
+                chars: 0..1
+            },
+            Span {
+                ty: TokenType::Whitespace(' '),
+                bytes: 2..3,
+                chars: 1..2
+            },
+            Span {
+                ty: TokenType::Punctuation('='),
+                bytes: 3..4,
+                chars: 2..3
+            },
+            Span {
+                ty: TokenType::Whitespace(' '),
+                bytes: 4..5,
+                chars: 3..4
+            },
+            Span {
+                ty: TokenType::Str('"'),
+                bytes: 5..9,
+                chars: 4..7
+            },
+            Span {
+                ty: TokenType::Punctuation(';'),
+                bytes: 9..10,
+                chars: 7..8
+            },
+        ]
+    );
+    let tokens = Token::default().tokens(&Syntax::rust(), text);
+    for (span, token) in spans.iter().zip(tokens) {
+        assert_eq!(span.slice(text), token.buffer());
+    }
+
+    // Spans cover the whole text, also with numbers after non-ASCII chars.
+    for text in ["€10", "x = ±1;", "€0x1F", "±0xg 1e €1e5", text] {
+        let spans = Syntax::rust().spans(text).collect::<Vec<_>>();
+        let bytes: Vec<_> = spans
+            .iter()
+            .flat_map(|s| [s.bytes.start, s.bytes.end])
+            .collect();
+        assert_eq!(bytes.first(), Some(&0), "{text}");
+        assert_eq!(bytes.last(), Some(&text.len()), "{text}");
+        assert!(
+            bytes[1..bytes.len() - 1].chunks(2).all(|b| b[0] == b[1]),
+            "{text}"
+        );
+    }
+}
+
+#[test]
+fn nested_comments() {
+    let text = "/* a /* b */ c */ d";
+    assert_eq!(
+        Token::default().tokens(&Syntax::rust(), text),
+        [
+            Token::new(TokenType::Comment(true), "/* a /* b */ c */"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Literal, "d"),
+        ]
+    );
+    assert_eq!(
+        Token::default().tokens(&Syntax::rust().with_nested_comments(false), text)[0],
+        Token::new(TokenType::Comment(true), "/* a /* b */")
+    );
+    assert_eq!(
+        Token::default().tokens(&Syntax::rust(), "/*/ a */")[0],
+        Token::new(TokenType::Comment(true), "/*/ a */")
+    );
+}
+
+#[test]
+fn comment_markers() {
+    let lua = Syntax::lua();
+    assert_eq!(
+        Token::default().tokens(&lua, "--[[ a\nb ]] --[==[ c ]] ]==] -- d"),
+        [
+            Token::new(TokenType::Comment(true), "--[[ a\nb ]]"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Comment(true), "--[==[ c ]] ]==]"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Comment(false), "-- d"),
+        ]
+    );
+    let sql = Syntax::sql();
+    assert_eq!(
+        Token::default().tokens(&sql, "x#b\n--c"),
+        [
+            Token::new(TokenType::Literal, "x"),
+            Token::new(TokenType::Comment(false), "#b"),
+            Token::new(TokenType::Whitespace('\n'), "\n"),
+            Token::new(TokenType::Comment(false), "--c"),
+        ]
+    );
+    let asm = Syntax::asm();
+    assert_eq!(
+        Token::default().tokens(&asm, "; a\n// b")[2],
+        Token::new(TokenType::Comment(false), "// b")
+    );
+}
+
+#[test]
+fn string_delimiters() {
+    let rust = Syntax::rust();
+    assert_eq!(
+        Token::default().tokens(&rust, r###"r#"a "b" c"# br##"d"#"## "\\" x"###),
+        [
+            Token::new(TokenType::Str('"'), r##"r#"a "b" c"#"##),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('"'), r###"br##"d"#"##"###),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('"'), "\""),
+            Token::new(TokenType::Escape, r"\\"),
+            Token::new(TokenType::Str('"'), "\""),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Literal, "x"),
+        ]
+    );
+    let python = Syntax::python();
+    assert_eq!(
+        Token::default().tokens(&python, "\"\"\"doc\n'x'\"\"\" b'{y}' ''"),
+        [
+            Token::new(TokenType::Str('"'), "\"\"\"doc\n'x'\"\"\""),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('\''), "b'{y}'"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('\''), "''"),
+        ]
+    );
+    let lua = Syntax::lua();
+    assert_eq!(
+        Token::default().tokens(&lua, "[==[ a ]] ]==]"),
+        [Token::new(TokenType::Str('['), "[==[ a ]] ]==]")]
+    );
+    let shell = Syntax::shell();
+    assert_eq!(
+        Token::default().tokens(&shell, "cat <<EOF\n'$x'\nEOF\n'a\\'"),
+        [
+            Token::new(TokenType::Literal, "cat"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('<'), "<<EOF\n'$x'\nEOF"),
+            Token::new(TokenType::Whitespace('\n'), "\n"),
+            Token::new(TokenType::Str('\''), "'a\\'"),
+        ]
+    );
+
+    let strings = [StringDelimiter::new("<<", ">>")];
+    let quotes_last = Syntax::default()
+        .with_strings(strings.clone())
+        .with_quotes(['@']);
+    let strings_last = Syntax::default().with_quotes(['@']).with_strings(strings);
+    assert_eq!(quotes_last, strings_last);
+    assert_eq!(
+        Token::default().tokens(&quotes_last, "<<a>> @b@"),
+        [
+            Token::new(TokenType::Str('<'), "<<a>>"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('@'), "@b@"),
+        ]
+    );
+}
+
+#[test]
+fn interpolation() {
+    let python = Syntax::python();
+    assert_eq!(
+        Token::default().tokens(&python, "f'a{x[\"k\"]}{{b}}'"),
+        [
+            Token::new(TokenType::Str('\''), "f'a"),
+            Token::new(TokenType::Interpolation, "{"),
+            Token::new(TokenType::Literal, "x"),
+            Token::new(TokenType::Punctuation('['), "["),
+            Token::new(TokenType::Str('"'), "\"k\""),
+            Token::new(TokenType::Punctuation(']'), "]"),
+            Token::new(TokenType::Interpolation, "}"),
+            Token::new(TokenType::Str('\''), "{{b}}'"),
+        ]
+    );
+    let rust = Syntax::rust();
+    assert_eq!(
+        Token::default().tokens(&rust, r#""{not code}" b"{x}""#),
+        [
+            Token::new(TokenType::Str('"'), "\"{not code}\""),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('"'), "b\"{x}\""),
+        ]
+    );
+    let shell = Syntax::shell();
+    assert_eq!(
+        Token::default().tokens(&shell, "\"$HOME/${dir}\""),
+        [
+            Token::new(TokenType::Str('"'), "\""),
+            Token::new(TokenType::Interpolation, "$"),
+            Token::new(TokenType::Type, "HOME"),
+            Token::new(TokenType::Str('"'), "/"),
+            Token::new(TokenType::Interpolation, "${"),
+            Token::new(TokenType::Literal, "dir"),
+            Token::new(TokenType::Interpolation, "}"),
+            Token::new(TokenType::Str('"'), "\""),
+        ]
+    );
+}
+
+#[test]
+fn escapes() {
+    let rust = Syntax::rust();
+    assert_eq!(
+        Token::default().tokens(&rust, r#""a\n\x41\u{1F600}\q" r"\n""#),
+        [
+            Token::new(TokenType::Str('"'), "\"a"),
+            Token::new(TokenType::Escape, r"\n"),
+            Token::new(TokenType::Escape, r"\x41"),
+            Token::new(TokenType::Escape, r"\u{1F600}"),
+            Token::new(TokenType::Str('"'), r#"\q""#),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('"'), r#"r"\n""#),
+        ]
+    );
+    let python = Syntax::python();
+    assert_eq!(
+        Token::default().tokens(&python, "'%5.2f%%'"),
+        [
+            Token::new(TokenType::Str('\''), "'"),
+            Token::new(TokenType::Escape, "%5.2f"),
+            Token::new(TokenType::Escape, "%%"),
+            Token::new(TokenType::Str('\''), "'"),
+        ]
+    );
+}
+
+#[test]
+fn numeric_literals() {
+    let rust = Syntax::rust();
+    assert_eq!(
+        Token::default().tokens(&rust, "0xFF 0b1010 1e-9 1_000_000 1..2"),
+        [
+            Token::new(TokenType::Numeric(false), "0xFF"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Numeric(false), "0b1010"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Numeric(true), "1e-9"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Numeric(false), "1_000_000"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Numeric(false), "1"),
+            Token::new(TokenType::Operator, ".."),
+            Token::new(TokenType::Numeric(false), "2"),
+        ]
+    );
+    let numbers = &rust.numbers;
+    assert_eq!(numbers.len_at("0xg"), None);
+    assert_eq!(numbers.len_at("0b"), None);
+    assert_eq!(numbers.len_at("1e"), None);
+    assert_eq!(numbers.len_at("1e+"), None);
+    assert_eq!(numbers.len_at("1f32"), Some((4, false)));
+    assert_eq!(Syntax::sql().numbers.len_at("0b1"), Some((3, false)));
+    assert_eq!(
+        Token::default().tokens(&rust, "0xg 1e 0b"),
+        [
+            Token::new(TokenType::Literal, "0xg"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Literal, "1e"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Literal, "0b"),
+        ]
+    );
+    let asm = Syntax::asm();
+    assert_eq!(
+        Token::default().tokens(&asm, "0FFh 1010b"),
+        [
+            Token::new(TokenType::Numeric(false), "0FFh"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Numeric(false), "1010b"),
+        ]
+    );
+}
+
+#[test]
+fn operators() {
+    let rust = Syntax::rust();
+    assert_eq!(
+        Token::default().tokens(&rust, "a::b->c<<=d!=e(f)"),
+        [
+            Token::new(TokenType::Literal, "a"),
+            Token::new(TokenType::Operator, "::"),
+            Token::new(TokenType::Literal, "b"),
+            Token::new(TokenType::Operator, "->"),
+            Token::new(TokenType::Literal, "c"),
+            Token::new(TokenType::Operator, "<<="),
+            Token::new(TokenType::Literal, "d"),
+            Token::new(TokenType::Operator, "!="),
+            Token::new(TokenType::Function, "e"),
+            Token::new(TokenType::Punctuation('('), "("),
+            Token::new(TokenType::Literal, "f"),
+            Token::new(TokenType::Punctuation(')'), ")"),
+        ]
+    );
+}
+
+#[test]
+fn rust_lifetimes_attributes_macros() {
+    let rust = Syntax::rust();
+    assert_eq!(
+        Token::default().tokens(&rust, "&'a str 'b' '\\''"),
+        [
+            Token::new(TokenType::Punctuation('&'), "&"),
+            Token::new(TokenType::Lifetime, "'a"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Type, "str"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('\''), "'b'"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('\''), "'"),
+            Token::new(TokenType::Escape, "\\'"),
+            Token::new(TokenType::Str('\''), "'"),
+        ]
+    );
+    assert_eq!(
+        Token::default().tokens(&rust, "#[derive(Debug)]"),
+        [
+            Token::new(TokenType::Attribute, "#[derive"),
+            Token::new(TokenType::Punctuation('('), "("),
+            Token::new(TokenType::Literal, "Debug"),
+            Token::new(TokenType::Punctuation(')'), ")"),
+            Token::new(TokenType::Punctuation(']'), "]"),
+        ]
+    );
+    assert_eq!(
+        Token::default().tokens(&rust, "println!(a!=b)"),
+        [
+            Token::new(TokenType::Macro, "println!"),
+            Token::new(TokenType::Punctuation('('), "("),
+            Token::new(TokenType::Literal, "a"),
+            Token::new(TokenType::Operator, "!="),
+            Token::new(TokenType::Literal, "b"),
+            Token::new(TokenType::Punctuation(')'), ")"),
+        ]
+    );
+}
+
+#[test]
+fn pattern_rules() {
+    use crate::Pattern;
+
+    let dsl = Syntax::new("DSL").with_patterns([
+        (Pattern::new("@\\w+").unwrap(), TokenType::Special),
+        (Pattern::new("\\$[A-Z_]+").unwrap(), TokenType::Type),
+        (Pattern::new("^#define.*").unwrap(), TokenType::Keyword),
+        (
+            Pattern::new("\\d{4}-\\d{2}-\\d{2}").unwrap(),
+            TokenType::Numeric(false),
+        ),
+    ]);
+    assert_eq!(
+        Token::default().tokens(&dsl, "#define X\n@note $VAR 2024-01-31 #define"),
+        [
+            Token::new(TokenType::Keyword, "#define X"),
+            Token::new(TokenType::Whitespace('\n'), "\n"),
+            Token::new(TokenType::Special, "@note"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Type, "$VAR"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Numeric(false), "2024-01-31"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Punctuation('#'), "#"),
+            Token::new(TokenType::Literal, "define"),
+        ]
+    );
+    let pattern = Pattern::new("(ab|a)[^x-z]?c{1,2}$").unwrap();
+    assert_eq!(pattern.len_at("abcc", true), Some(4));
+    assert_eq!(pattern.len_at("ac\nc", true), Some(2));
+    assert_eq!(pattern.len_at("axc", true), None);
+    assert!(Pattern::new("a{2,1}").is_err());
+    assert!(Pattern::new("(a").is_err());
+
+    let long = "a".repeat(100_000);
+    let len_at = |source: &'static str| Pattern::new(source).unwrap().len_at(&long, true);
+    assert_eq!(len_at(".*"), Some(long.len()));
+    assert_eq!(len_at("[a-z]+$"), Some(long.len()));
+    assert_eq!(len_at("a+b?"), Some(long.len()));
+    // Repeated groups give up instead of overflowing the stack.
+    assert_eq!(len_at("(a)*"), None);
+    assert_eq!(len_at("(ab|a)+$"), None);
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn load_toml() {
+    let syntax = Syntax::from_toml(
+        r#"
+language = "Notes"
+keywords = ["let"]
+operators = ["->"]
+patterns = [["@\\w+", "Special"]]
+
+[[strings]]
+open = '"""'
+close = '"""'
+interpolations = [{ open = "${", close = "}" }]
+"#,
+    )
+    .unwrap();
+    assert_eq!(syntax.language(), "Notes");
+    assert_eq!(syntax.comment(), "//");
+    assert_eq!(
+        Token::default().tokens(&syntax, "let @a->\"\"\"${b}\"\"\""),
+        [
+            Token::new(TokenType::Keyword, "let"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Special, "@a"),
+            Token::new(TokenType::Operator, "->"),
+            Token::new(TokenType::Str('"'), "\"\"\""),
+            Token::new(TokenType::Interpolation, "${"),
+            Token::new(TokenType::Literal, "b"),
+            Token::new(TokenType::Interpolation, "}"),
+            Token::new(TokenType::Str('"'), "\"\"\""),
+        ]
+    );
+    assert!(Syntax::from_toml("patterns = [[\"(\", \"Special\"]]").is_err());
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn load_json() {
+    let syntax = Syntax::from_json(r##"{"language": "J", "comment": "#"}"##).unwrap();
+    assert_eq!(
+        Token::default().tokens(&syntax, "1 # x"),
+        [
+            Token::new(TokenType::Numeric(false), "1"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Comment(false), "# x"),
+        ]
+    );
+}
+
+#[cfg(feature = "textmate")]
+#[test]
+fn textmate_grammar() {
+    let grammar = r##"{
+        "name": "Mini",
+        "scopeName": "source.mini",
+        "patterns": [
+            { "include": "#comments" },
+            { "name": "keyword.control.mini", "match": "\\b(if|else|while)\\b" },
+            { "name": "keyword.operator.mini", "match": "==|\\+=" },
+            { "name": "constant.language.mini", "match": "\\b(?:true|false)\\b" },
+            { "name": "variable.other.mini", "match": "\\$\\w+" },
+            { "name": "support.function.mini", "match": "(?<=\\.)\\w+" },
+            { "name": "entity.name.function.mini", "match": "@\\w+" },
+            {
+                "name": "string.quoted.double.mini",
+                "begin": "\"",
+                "end": "\"",
+                "patterns": [{ "name": "constant.character.escape.mini", "match": "\\\\." }]
+            },
+            { "name": "string.quoted.single.mini", "begin": "'", "end": "'", "patterns": [{ "include": "#escapes" }] },
+            { "name": "string.quoted.other.mini", "begin": "`", "end": "`", "patterns": [{ "name": "markup.escape-free.mini", "match": "x" }] }
+        ],
+        "repository": {
+            "escapes": { "patterns": [{ "scope": "constant.character.escape.mini", "match": "\\\\." }] },
+            "comments": {
+                "patterns": [
+                    { "name": "comment.line.number-sign.mini", "match": "(#).*$" },
+                    { "name": "comment.block.mini", "begin": "\\(\\*", "end": "\\*\\)" }
+                ]
+            }
+        }
+    }"##;
+    let syntax = Syntax::from_textmate(grammar).unwrap();
+    assert_eq!(syntax.language(), "Mini");
+    assert_eq!(syntax.comment(), "#");
+    assert_eq!(syntax.comments_multiline().next(), Some(["(*", "*)"]));
+    assert!(syntax.is_keyword("while") && syntax.is_special("true"));
+    let escapes: Vec<_> = syntax
+        .strings
+        .iter()
+        .map(|s| (s.open.as_ref(), s.escape))
+        .collect();
+    assert_eq!(
+        escapes,
+        [("\"", Some('\\')), ("'", Some('\\')), ("`", None)]
+    );
+    assert_eq!(
+        Token::default().tokens(&syntax, "if @f += \"a\" (* c *)"),
+        [
+            Token::new(TokenType::Keyword, "if"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Function, "@f"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Operator, "+="),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('"'), "\"a\""),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Comment(true), "(* c *)"),
+        ]
+    );
+    let error = |json| Syntax::from_textmate(json).unwrap_err().to_string();
+    assert_eq!(
+        error("[]"),
+        "invalid TextMate grammar: grammar is not a JSON object"
+    );
+    assert_eq!(
+        error(r#"{"patterns": [{"name": "keyword", "match": 1}]}"#),
+        "invalid TextMate grammar: `grammar.patterns[0].match` is not a string"
+    );
+    assert!(Syntax::from_textmate("{").is_err());
+}
+
+#[test]
+fn syntax_registry() {
+    use crate::{SyntaxEntry, SyntaxRegistry};
+
+    let mut registry = SyntaxRegistry::default();
+    let language = |s: Option<&Syntax>| s.map(|s| s.language().to_string());
+    assert_eq!(language(registry.by_name("python")), Some("Python".into()));
+    assert_eq!(language(registry.by_extension("RS")), Some("Rust".into()));
+    assert_eq!(
+        language(registry.by_shebang("#!/usr/bin/env -S python3.12 -u")),
+        Some("Python".into())
+    );
+    assert_eq!(
+        language(registry.by_shebang("#!/bin/bash")),
+        Some("Shell".into())
+    );
+    assert_eq!(
+        language(registry.guess("local x = 1\nif x ~= 2 then\nend\n")),
+        Some("Lua".into())
+    );
+    assert_eq!(
+        language(registry.detect("build", "#!/bin/sh\necho hi\n")),
+        Some("Shell".into())
+    );
+    assert_eq!(registry.guess("plain text"), None);
+
+    registry.register(SyntaxEntry::new(Syntax::new("Notes")).with_extensions(["txt", "rs"]));
+    assert_eq!(language(registry.by_extension("txt")), Some("Notes".into()));
+    assert_eq!(language(registry.detect("a.rs", "")), Some("Notes".into()));
+}
+
+#[test]
+fn injections() {
+    use crate::Injection;
+
+    let rust = Syntax::rust().with_injections([Injection::marked("sql", Syntax::sql())]);
+    assert_eq!(
+        Token::default().tokens(&rust, "/* SQL */ r#\"SELECT \"a\"\"#; \"x\""),
+        [
+            Token::new(TokenType::Comment(true), "/* SQL */"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('"'), "r#\""),
+            Token::new(TokenType::Keyword, "SELECT"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('"'), "\"a\""),
+            Token::new(TokenType::Str('"'), "\"#"),
+            Token::new(TokenType::Punctuation(';'), ";"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('"'), "\"x\""),
+        ]
+    );
+    assert_eq!(
+        Token::default().tokens(&rust, "// sql\n\"1\\n\""),
+        [
+            Token::new(TokenType::Comment(false), "// sql"),
+            Token::new(TokenType::Whitespace('\n'), "\n"),
+            Token::new(TokenType::Str('"'), "\""),
+            Token::new(TokenType::Numeric(false), "1"),
+            Token::new(TokenType::Punctuation('\\'), "\\"),
+            Token::new(TokenType::Literal, "n"),
+            Token::new(TokenType::Str('"'), "\""),
+        ]
+    );
+    let markdown =
+        Syntax::simple("").with_injections([Injection::region("```lua", "```", Syntax::lua())]);
+    assert_eq!(
+        Token::default().tokens(&markdown, "Code:\n```lua\nlocal x = \"\\n\"\n```"),
+        [
+            Token::new(TokenType::Literal, "Code"),
+            Token::new(TokenType::Punctuation(':'), ":"),
+            Token::new(TokenType::Whitespace('\n'), "\n"),
+            Token::new(TokenType::Str('a'), "```lua"),
+            Token::new(TokenType::Whitespace('\n'), "\n"),
+            Token::new(TokenType::Keyword, "local"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Literal, "x"),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Punctuation('='), "="),
+            Token::new(TokenType::Whitespace(' '), " "),
+            Token::new(TokenType::Str('"'), "\""),
+            Token::new(TokenType::Escape, "\\n"),
+            Token::new(TokenType::Str('"'), "\""),
+            Token::new(TokenType::Whitespace('\n'), "\n"),
+            Token::new(TokenType::Str('a'), "```"),
+        ]
+    );
+}
+
+#[test]
+fn bracket_matching() {
+    use crate::matching_bracket;
+
+    let syntax = Syntax::rust();
+    let text = "f(a[0], \")\", { b(c) }) // (";
+    assert_eq!(matching_bracket(text, &syntax, 1), Some(21));
+    assert_eq!(matching_bracket(text, &syntax, 21), Some(1));
+    assert_eq!(matching_bracket(text, &syntax, 3), Some(5));
+    assert_eq!(matching_bracket(text, &syntax, 13), Some(20));
+    assert_eq!(matching_bracket(text, &syntax, 18), Some(16));
+    assert_eq!(matching_bracket(text, &syntax, 9), None);
+    assert_eq!(matching_bracket(text, &syntax, 26), None);
+    assert_eq!(matching_bracket(text, &syntax, 0), None);
+    assert_eq!(matching_bracket("(()", &syntax, 0), None);
+    let attribute = "#[derive(Debug)] #![allow(x)]";
+    assert_eq!(matching_bracket(attribute, &syntax, 1), Some(15));
+    assert_eq!(matching_bracket(attribute, &syntax, 28), Some(19));
+
+    use crate::brackets::{bracket_pairs, brackets};
+    for text in [text, attribute, "(()", "{ ( ] } ) [ ( ) ] ]", ") ( ) ("] {
+        let pairs = bracket_pairs(brackets(text, &syntax));
+        let expected: Vec<_> = (0..text.chars().count())
+            .filter_map(|i| Some((i, matching_bracket(text, &syntax, i)?)))
+            .collect();
+        assert_eq!(pairs, expected, "{text}");
+    }
+}
+
+#[test]
+fn bracket_depths() {
+    use crate::brackets::{bracket_depths, brackets};
+
+    let syntax = Syntax::lua();
+    let text = "t = { a = { 1, (2) }, \"}\" ] } }";
+    assert_eq!(
+        bracket_depths(brackets(text, &syntax)),
+        [
+            (4, Some(0)),
+            (10, Some(1)),
+            (15, Some(2)),
+            (17, Some(2)),
+            (19, Some(1)),
+            (26, None),
+            (28, Some(0)),
+            (30, None),
+        ]
+    );
+    assert_eq!(
+        bracket_depths(brackets("((]", &syntax)),
+        [(0, None), (1, None), (2, None)]
+    );
+    assert_eq!(
+        bracket_depths(brackets("#[cfg(test)]", &Syntax::rust())),
+        [(1, Some(0)), (5, Some(1)), (10, Some(1)), (11, Some(0))]
+    );
+}
+
+#[test]
+fn fold_regions() {
+    use crate::fold_regions;
+    use crate::folding::{hidden_lines, line_bytes};
+    use std::collections::BTreeSet;
+
+    let python = "# region setup\nimport os\n\n# endregion\ndef f(x):\n    if x:\n        return [\n            1,\n        ]\n\n    return 0\nf(1)";
+    let regions = fold_regions(
+        python,
+        &Syntax::python().with_fold_markers([["# region", "# endregion"]]),
+    );
+    assert_eq!(regions, [0..4, 4..11, 5..9, 6..8]);
+    let hidden = hidden_lines(&regions, &BTreeSet::from([0, 4, 6]));
+    assert_eq!(hidden, [1..4, 5..11]);
+    assert_eq!(line_bytes("a\nb\nc", &[1..2, 2..3]), [2..4, 4..5]);
+
+    let rust = "fn main() {\n    // #region\n    let v = vec![\n        1,\n    ];\n    // #endregion\n}\n";
+    assert_eq!(fold_regions(rust, &Syntax::rust()), [0..6, 1..6, 2..4]);
+}
+
+#[test]
+fn diagnostics() {
+    use crate::diagnostics::line_severities;
+    use crate::{Diagnostic, Severity};
+
+    let text = "let x = 1;\nlet y: u8 = x;\n";
+    let diagnostics = [
+        Diagnostic::new(4..5, Severity::Warning, "unused variable `x`"),
+        Diagnostic::new(23..24, Severity::Hint, "consider casting"),
+        Diagnostic::new(23..23, Severity::Error, "mismatched types").with_code("E0308"),
+    ];
+    assert_eq!(
+        line_severities(text, &diagnostics),
+        [Some(Severity::Warning), Some(Severity::Error), None]
+    );
+    assert!(diagnostics[2].contains(23));
+    assert!(!diagnostics[2].contains(24));
+    assert_eq!(diagnostics[2].to_string(), "Error[E0308]: mismatched types");
+
+    #[cfg(feature = "egui")]
+    {
+        use crate::diagnostics::underline_diagnostics;
+        use egui::{Color32, TextFormat, text::LayoutJob};
+
+        let mut job = LayoutJob::single_section(text.to_string(), TextFormat::default());
+        underline_diagnostics(&mut job, text, &diagnostics, |severity| match severity {
+            Severity::Error => Color32::RED,
+            _ => Color32::YELLOW,
+        });
+        let underlined: Vec<(&str, Color32)> = job
+            .sections
+            .iter()
+            .filter(|s| s.format.underline.width > 0.0)
+            .map(|s| {
+                (
+                    &text[s.byte_range.start.0..s.byte_range.end.0],
+                    s.format.underline.color,
+                )
+            })
+            .collect();
+        assert_eq!(underlined, [("x", Color32::YELLOW), ("x", Color32::RED)]);
+    }
+}
+
+#[test]
+fn search() {
+    use crate::Search;
+    use crate::search::nearest_match;
+
+    let text = "Foo foo food\nfoo.bar(foo)";
+    let search = Search::new("foo");
+    assert_eq!(search.matches(text), Ok(vec![4..7, 8..11, 13..16, 21..24]));
+    let search = search.with_case_insensitive(true);
+    assert_eq!(search.matches(text).unwrap().len(), 5);
+    let search = search.with_whole_word(true);
+    assert_eq!(search.matches(text), Ok(vec![0..3, 4..7, 13..16, 21..24]));
+    assert_eq!(
+        search.replace_all(text, "x"),
+        Ok("x x food\nx.bar(x)".to_string())
+    );
+
+    assert_eq!(Search::new(".").matches("a.b.c"), Ok(vec![1..2, 3..4]));
+    let regex = Search::new(r"^\w+").with_regex(true);
+    assert_eq!(regex.matches(text), Ok(vec![0..3, 13..16]));
+    assert!(Search::new("(").with_regex(true).matches(text).is_err());
+    assert_eq!(Search::new("").matches(text), Ok(vec![]));
+    let backtracking = Search::new("(a*)*b").with_regex(true);
+    assert_eq!(backtracking.matches(&"a".repeat(40)), Ok(vec![]));
+    assert_eq!(backtracking.matches("aab").unwrap().first(), Some(&(0..3)));
+    let long = format!("{}\nb", "a".repeat(40_000));
+    let lines = Search::new(".*").with_regex(true);
+    assert_eq!(lines.matches(&long), Ok(vec![0..40_000, 40_001..40_002]));
+    let words = Search::new(r"\w+$").with_regex(true);
+    assert_eq!(words.matches(&long), Ok(vec![0..40_000, 40_001..40_002]));
+
+    let matches = [0..3, 4..7, 13..16];
+    assert_eq!(nearest_match(&matches, 3, false), Some(1));
+    assert_eq!(nearest_match(&matches, 14, false), Some(0));
+    assert_eq!(nearest_match(&matches, 4, true), Some(0));
+    assert_eq!(nearest_match(&matches, 0, true), Some(2));
+}
+
+#[test]
+#[cfg(feature = "egui")]
+fn marked_lines() {
+    let ctx = egui::Context::default();
+    let editor = CodeEditor::default().id_source("marks");
+    let mut text = String::from("a\nb\nc");
+    let _ = ctx.run_ui(egui::RawInput::default(), |ui| {
+        let mut marking = editor.clone().with_marked_lines([1]);
+        marking.show(ui, &mut text, &Syntax::rust());
+        assert_eq!(marking.marked_lines(), Some(&BTreeSet::from([1])));
+    });
+    assert_eq!(editor.load_marked_lines(&ctx), BTreeSet::from([1]));
+    let _ = ctx.run_ui(egui::RawInput::default(), |ui| {
+        editor.clone().show(ui, &mut text, &Syntax::rust());
+    });
+    assert_eq!(editor.load_marked_lines(&ctx), BTreeSet::from([1]));
+}
+
+#[test]
+#[cfg(feature = "egui")]
+fn multi_cursor() {
+    use crate::cursors::{Edit, apply_edit, box_selection, line_column, next_occurrence};
+
+    let mut text = String::from("SELECT id,\n       name,\n       age");
+    let mut cursors = box_selection(&text, (0, 7), (2, 7));
+    assert_eq!(cursors, [7..7, 18..18, 31..31]);
+    apply_edit(&mut text, &mut cursors, &Edit::Insert("t.".into()));
+    assert_eq!(text, "SELECT t.id,\n       t.name,\n       t.age");
+    assert_eq!(cursors, [9..9, 22..22, 37..37]);
+    apply_edit(&mut text, &mut cursors, &Edit::Backspace);
+    apply_edit(&mut text, &mut cursors, &Edit::Insert("a\nb\nc".into()));
+    assert_eq!(text, "SELECT taid,\n       tbname,\n       tcage");
+    assert_eq!(cursors, [9..9, 22..22, 37..37]);
+    assert_eq!(line_column(&text, 22), (1, 9));
+
+    let mut text = String::from("ab\ncd");
+    let reversed = |caret, anchor| std::ops::Range {
+        start: anchor,
+        end: caret,
+    };
+    let mut cursors = [reversed(0, 1), reversed(3, 4)];
+    apply_edit(&mut text, &mut cursors, &Edit::Delete);
+    assert_eq!(text, "b\nd");
+    let mut stale = [reversed(6, 3), 9..9];
+    apply_edit(&mut text, &mut stale, &Edit::Insert("x".into()));
+    assert_eq!((text.as_str(), stale), ("b\ndxx", [4..4, 5..5]));
+
+    use crate::cursors::MultiCursor;
+    let mut multi = MultiCursor::default();
+    multi.sync(&text);
+    multi.extra.push(1..1);
+    multi.sync(&text);
+    assert_eq!(multi.extra.first(), Some(&(1..1)));
+    multi.sync("b\nd");
+    assert!(multi.extra.is_empty());
+    assert_eq!(
+        box_selection("abc\nd", (1, 2), (0, 1)),
+        [reversed(1, 2), 5..5]
+    );
+    assert_eq!(box_selection("\tab\n    cd", (0, 5), (1, 6)), [2..3, 9..10]);
+
+    use crate::cursors::move_cursor;
+    use egui::Key;
+    let text = "abc\nd\nefgh";
+    assert_eq!(move_cursor(text, 2..2, Key::ArrowDown, false), 5..5);
+    assert_eq!(move_cursor(text, 5..5, Key::ArrowDown, false), 7..7);
+    assert_eq!(move_cursor(text, 1..3, Key::ArrowLeft, false), 1..1);
+    assert_eq!(move_cursor(text, 1..3, Key::ArrowLeft, true), 1..2);
+    assert_eq!(move_cursor(text, 8..8, Key::Home, true), reversed(6, 8));
+    assert_eq!(move_cursor(text, 8..8, Key::End, false), 10..10);
+    assert_eq!(move_cursor(text, 1..1, Key::ArrowUp, false), 0..0);
+
+    let text = "foo bar foo baz foo";
+    assert_eq!(
+        next_occurrence(text, "foo", 3, &[0..3, 16..19]),
+        Some(8..11)
+    );
+    assert_eq!(
+        next_occurrence(text, "foo", 19, &[0..3, 8..11]),
+        Some(16..19)
+    );
+    assert_eq!(
+        next_occurrence(text, "foo", 19, &[0..3, reversed(8, 11), 16..19]),
+        None
+    );
+}
+
+#[test]
+fn undo_history() {
+    use crate::{EditKind, Grouping, History};
+
+    let mut history = History::default();
+    let mut text = String::new();
+    history.update(&text, EditKind::Other);
+    for (typed, kind) in [
+        ("let", EditKind::Typing),
+        (" ", EditKind::Typing),
+        ("co", EditKind::Typing),
+        ("unt", EditKind::Completion),
+        (" = 1;", EditKind::Paste),
+        ("\n", EditKind::Typing),
+    ] {
+        text.push_str(typed);
+        history.update(&text, kind);
+    }
+    text.pop();
+    history.update(&text, EditKind::Typing);
+    assert_eq!(history.undo_steps().len(), 5);
+
+    assert_eq!(history.undo(&mut text), Some(15));
+    assert_eq!(text, "let count = 1;\n");
+    assert_eq!(history.undo(&mut text), Some(14));
+    assert_eq!(text, "let count = 1;");
+    assert_eq!(history.undo(&mut text), Some(9));
+    assert_eq!(text, "let count");
+    assert_eq!(history.undo(&mut text), Some(4));
+    assert_eq!(text, "let ");
+    assert_eq!(history.redo(&mut text), Some(9));
+    assert_eq!(text, "let count");
+    history.update(&text, EditKind::Other);
+    assert!(history.can_redo());
+
+    let mut history = History::new(Grouping {
+        words: false,
+        ..Default::default()
+    });
+    history.update("", EditKind::Other);
+    history.update("a", EditKind::Typing);
+    history.update("ab", EditKind::Typing);
+    assert_eq!(history.undo_steps().len(), 2);
+}
+
+#[test]
+#[cfg(feature = "egui")]
+fn line_operations() {
+    use crate::LineOp;
+    use crate::lines::{apply_at_cursors, consume_exact};
+
+    let mut text = String::from("one\ntwo\nthree");
+    assert_eq!(LineOp::Duplicate.apply(&mut text, 5..5), 9..9);
+    assert_eq!(text, "one\ntwo\ntwo\nthree");
+    assert_eq!(LineOp::MoveDown.apply(&mut text, 0..5), 4..9);
+    assert_eq!(text, "two\none\ntwo\nthree");
+    assert_eq!(LineOp::MoveUp.apply(&mut text, 0..1), 0..1);
+    assert_eq!(LineOp::MoveUp.apply(&mut text, 14..14), 10..10);
+    assert_eq!(text, "two\none\nthree\ntwo");
+    assert_eq!(LineOp::Delete.apply(&mut text, 4..8), 4..4);
+    assert_eq!(text, "two\nthree\ntwo");
+    assert_eq!(LineOp::Delete.apply(&mut text, 11..11), 4..4);
+    assert_eq!(text, "two\nthree");
+
+    let mut text = String::from("fn f(\n    a,  \n    b,\n) {}");
+    assert_eq!(LineOp::Join.apply(&mut text, 0..0), 5..5);
+    assert_eq!(text, "fn f( a,\n    b,\n) {}");
+    assert_eq!(LineOp::Join.apply(&mut text, 0..12), 0..11);
+    assert_eq!(text, "fn f( a, b,\n) {}");
+
+    let mut text = String::from("a\nb\nc\nd");
+    let cursors = [6..6, 0..0, 2..2];
+    let apply = |op: LineOp| move |text: &mut dyn egui::TextBuffer, s| op.apply(text, s);
+    let moved = apply_at_cursors(&mut text, &cursors, true, apply(LineOp::MoveDown));
+    assert_eq!(text, "c\na\nb\nd");
+    assert_eq!(moved, [6..6, 2..2, 4..4]);
+    let mut text = String::from("a\nb\nc\nd");
+    let deleted = apply_at_cursors(&mut text, &cursors, false, apply(LineOp::Delete));
+    assert_eq!(text, "c");
+    assert_eq!(deleted, [0..0, 0..0, 0..0]);
+
+    let ctx = egui::Context::default();
+    let key = |modifiers| egui::Event::Key {
+        key: egui::Key::J,
+        physical_key: None,
+        pressed: true,
+        repeat: false,
+        modifiers,
+    };
+    let join = LineOp::Join.shortcut();
+    let input = egui::RawInput {
+        events: vec![key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT)],
+        ..Default::default()
+    };
+    let _ = ctx.run_ui(input, |ui| {
+        assert!(!ui.input_mut(|i| consume_exact(i, &join)));
+    });
+    let input = egui::RawInput {
+        events: vec![key(egui::Modifiers::COMMAND)],
+        ..Default::default()
+    };
+    let _ = ctx.run_ui(input, |ui| {
+        assert!(ui.input_mut(|i| consume_exact(i, &join)));
+    });
+}
+
+#[test]
+#[cfg(feature = "egui")]
+fn toggle_comments() {
+    use crate::lines::{toggle_block_comment, toggle_line_comment};
+
+    let rust = Syntax::rust();
+    let mut text = String::from("fn f() {\n    let a = 1;\n\n        a\n}");
+    let selection = toggle_line_comment(&mut text, 13..35, &rust);
+    assert_eq!(text, "fn f() {\n    // let a = 1;\n\n    //     a\n}");
+    assert_eq!(selection, 13..41);
+    let selection = toggle_line_comment(&mut text, selection, &rust);
+    assert_eq!(text, "fn f() {\n    let a = 1;\n\n        a\n}");
+    assert_eq!(selection, 13..35);
+    assert_eq!(toggle_line_comment(&mut text, 0..0, &rust), 3..3);
+    assert_eq!(text, "// fn f() {\n    let a = 1;\n\n        a\n}");
+
+    let mut text = String::from("    let a = 1;");
+    assert_eq!(toggle_block_comment(&mut text, 2..2, &rust), 2..2);
+    assert_eq!(text, "    /* let a = 1; */");
+    assert_eq!(toggle_block_comment(&mut text, 2..2, &rust), 2..2);
+    assert_eq!(text, "    let a = 1;");
+    assert_eq!(toggle_block_comment(&mut text, 12..13, &rust), 12..19);
+    assert_eq!(text, "    let a = /* 1 */;");
+    assert_eq!(toggle_block_comment(&mut text, 12..19, &rust), 12..13);
+    assert_eq!(text, "    let a = 1;");
+
+    let mut text = String::from("x = 1\n# y = 2");
+    toggle_block_comment(&mut text, 0..13, &Syntax::python());
+    assert_eq!(text, "# x = 1\n# # y = 2");
+    toggle_line_comment(&mut text, 0..0, &Syntax::python());
+    assert_eq!(text, "x = 1\n# # y = 2");
+}
+
+#[test]
+#[cfg(feature = "egui")]
+fn smart_indent() {
+    use crate::lines::{Indent, dedent_typed, indent_lines, insert_newline, outdent_lines};
+
+    let spaces = Indent::Spaces(4);
+    let mut text = String::from("a\n\n  b\nc");
+    assert_eq!(indent_lines(&mut text, 0..8, spaces), 0..20);
+    assert_eq!(text, "    a\n\n      b\n    c");
+    assert_eq!(outdent_lines(&mut text, 0..20, spaces), 0..8);
+    assert_eq!(text, "a\n\n  b\nc");
+    let mut text = String::from("\t\tx");
+    assert_eq!(outdent_lines(&mut text, 3..3, Indent::Tabs), 2..2);
+    assert_eq!(text, "\tx");
+
+    let rust = Syntax::rust();
+    let mut text = String::from("fn f() {}");
+    assert_eq!(insert_newline(&mut text, 8..8, &rust, spaces), 13..13);
+    assert_eq!(text, "fn f() {\n    \n}");
+    let mut text = String::from("    let a = (");
+    assert_eq!(
+        insert_newline(&mut text, 13..13, &rust, Indent::Tabs),
+        19..19
+    );
+    assert_eq!(text, "    let a = (\n    \t");
+    let mut text = String::from("    a;");
+    assert_eq!(insert_newline(&mut text, 6..6, &rust, spaces), 11..11);
+    assert_eq!(text, "    a;\n    ");
+
+    let mut text = String::from("if a {\n    b\n    ");
+    assert_eq!(
+        dedent_typed(&mut text, 17..17, "}", &rust, spaces),
+        Some(14..14)
+    );
+    assert_eq!(text, "if a {\n    b\n}");
+    let mut text = String::from("if a {\n    b\n    c");
+    assert_eq!(dedent_typed(&mut text, 18..18, "}", &rust, spaces), None);
+
+    let python = Syntax::python();
+    let mut text = String::from("if a:");
+    assert_eq!(insert_newline(&mut text, 5..5, &python, spaces), 10..10);
+    assert_eq!(text, "if a:\n    ");
+    text.push_str("b\n    else");
+    assert_eq!(
+        dedent_typed(&mut text, 21..21, ":", &python, spaces),
+        Some(18..18)
+    );
+    assert_eq!(text, "if a:\n    b\nelse:");
+    let mut text = String::from("if a:\n    b\n    elsewhere");
+    assert_eq!(dedent_typed(&mut text, 25..25, "=", &python, spaces), None);
+
+    let lua = Syntax::lua();
+    let mut text = String::from("for i = 1, 3 do");
+    assert_eq!(insert_newline(&mut text, 15..15, &lua, spaces), 20..20);
+    text.push_str("f(i)\n    end");
+    assert_eq!(insert_newline(&mut text, 32..32, &lua, spaces), 29..29);
+    assert_eq!(text, "for i = 1, 3 do\n    f(i)\nend\n");
+    let mut text = String::from("if a then");
+    insert_newline(&mut text, 9..9, &lua, spaces);
+    assert_eq!(text, "if a then\n    ");
+}
+
+#[test]
+fn theme_defaults() {
+    let theme = ColorTheme {
+        name: "Mine",
+        strs: "ce9178",
+        ..ColorTheme::DEFAULT
+    };
+    assert_eq!(theme.type_color_str(TokenType::Escape), "ce9178");
+    assert_eq!(
+        theme.type_color_str(TokenType::Operator),
+        ColorTheme::DEFAULT.punctuation
+    );
+    assert_eq!(ColorTheme::default(), ColorTheme::GRUVBOX);
+}
@@ -18,6 +18,7 @@ impl ColorTheme {
         strs: "a5d6ff",        // scale.blue.1
         types: "ffa657",       // scale.orange.2
         special: "a5d6ff",     // scale.blue.1
+        ..ColorTheme::DEFAULT
     };
 
     pub const GITHUB_LIGHT: ColorTheme = ColorTheme {
@@ -35,5 +36,6 @@ impl ColorTheme {
         strs: "0a3069",        // scale.blue.8
         types: "953800",       // scale.orange.6
         special: "a475f9",     // scale.purple.4
+        ..ColorTheme::DEFAULT
     };
 }
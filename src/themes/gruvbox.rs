@@ -18,6 +18,10 @@ impl ColorTheme {
         strs: "8ec07c",        // aqua1
         types: "fabd2f",       // yellow1
         special: "83a598",     // blue1
+        escapes: None,
+        operators: None,
+        bracket_match: None,
+        rainbow: None,
     };
 
     pub const GRUVBOX_DARK: ColorTheme = ColorTheme::GRUVBOX;
@@ -37,5 +41,6 @@ impl ColorTheme {
         strs: "427b58",        // aqua1
         types: "b57614",       // yellow1
         special: "af3a03",     // orange1
+        ..ColorTheme::DEFAULT
     };
 }
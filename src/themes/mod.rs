@@ -4,12 +4,18 @@ pub mod github;
 pub mod gruvbox;
 pub mod sonokai;
 
+#[cfg(feature = "egui")]
+use super::diagnostics::Severity;
 use super::syntax::TokenType;
 #[cfg(feature = "egui")]
 use egui::Color32;
 
 #[cfg(feature = "egui")]
 pub const ERROR_COLOR: Color32 = Color32::from_rgb(255, 0, 255);
+#[cfg(feature = "egui")]
+pub const WARNING_COLOR: Color32 = Color32::from_rgb(230, 180, 0);
+#[cfg(feature = "egui")]
+pub const INFO_COLOR: Color32 = Color32::from_rgb(60, 150, 255);
 
 /// Array of default themes.
 pub const DEFAULT_THEMES: [ColorTheme; 8] = [
@@ -59,13 +65,26 @@ pub struct ColorTheme {
     pub strs: &'static str,
     pub types: &'static str,
     pub special: &'static str,
+    /// Escape sequences in strings, `strs` if `None`.
+    pub escapes: Option<&'static str>,
+    /// Operators, `punctuation` if `None`.
+    pub operators: Option<&'static str>,
+    /// Outline of the bracket at the cursor and its matching pair, `cursor` if `None`.
+    pub bracket_match: Option<&'static str>,
+    /// Bracket colors by nesting depth, taken in turn.
+    /// `functions`, `keywords`, `types` and `special` if `None`.
+    pub rainbow: Option<&'static [&'static str]>,
 }
 impl Default for ColorTheme {
     fn default() -> Self {
-        ColorTheme::GRUVBOX
+        ColorTheme::DEFAULT
     }
 }
 impl ColorTheme {
+    /// Base for themes written as struct literals, to set only the colors they change:
+    /// `ColorTheme { name: "Mine", bg: "000000", ..ColorTheme::DEFAULT }`.
+    pub const DEFAULT: ColorTheme = ColorTheme::GRUVBOX;
+
     pub fn name(&self) -> &str {
         self.name
     }
@@ -89,6 +108,36 @@ impl ColorTheme {
         color_from_hex(self.selection)
     }
 
+    #[cfg(feature = "egui")]
+    pub fn bracket_match(&self) -> Color32 {
+        color_from_hex(self.bracket_match.unwrap_or(self.cursor))
+    }
+
+    #[cfg(feature = "egui")]
+    /// Color of a bracket at nesting `depth`, [`ERROR_COLOR`] if unbalanced.
+    pub fn bracket_color(&self, depth: Option<usize>) -> Color32 {
+        let Some(depth) = depth else {
+            return ERROR_COLOR;
+        };
+        let default = [self.functions, self.keywords, self.types, self.special];
+        let rainbow = self.rainbow.unwrap_or(&default);
+        match rainbow.get(depth % rainbow.len().max(1)) {
+            Some(color) => color_from_hex(color),
+            None => color_from_hex(self.punctuation),
+        }
+    }
+
+    #[cfg(feature = "egui")]
+    /// Color of diagnostic squiggles and icons, hints are colored as comments.
+    pub fn severity_color(&self, severity: Severity) -> Color32 {
+        match severity {
+            Severity::Error => ERROR_COLOR,
+            Severity::Warning => WARNING_COLOR,
+            Severity::Info => INFO_COLOR,
+            Severity::Hint => color_from_hex(self.comments),
+        }
+    }
+
     #[cfg(feature = "egui")]
     pub fn modify_style(&self, ui: &mut egui::Ui, fontsize: f32) {
         let style = ui.style_mut();
@@ -106,15 +155,24 @@ impl ColorTheme {
 
     pub const fn type_color_str(&self, ty: TokenType) -> &'static str {
         match ty {
+            TokenType::Attribute => self.special,
             TokenType::Comment(_) => self.comments,
-            TokenType::Function => self.functions,
-            TokenType::Keyword => self.keywords,
+            TokenType::Function | TokenType::Macro => self.functions,
+            TokenType::Keyword | TokenType::Lifetime => self.keywords,
             TokenType::Literal => self.literals,
-            TokenType::Hyperlink => self.special,
+            TokenType::Hyperlink | TokenType::Interpolation => self.special,
             TokenType::Numeric(_) => self.numerics,
             TokenType::Punctuation(_) => self.punctuation,
+            TokenType::Operator => match self.operators {
+                Some(operators) => operators,
+                None => self.punctuation,
+            },
             TokenType::Special => self.special,
             TokenType::Str(_) => self.strs,
+            TokenType::Escape => match self.escapes {
+                Some(escapes) => escapes,
+                None => self.strs,
+            },
             TokenType::Type => self.types,
             TokenType::Whitespace(_) | TokenType::Unknown => self.comments,
         }
@@ -123,15 +181,18 @@ impl ColorTheme {
     #[cfg(feature = "egui")]
     pub fn type_color(&self, ty: TokenType) -> Color32 {
         match ty {
+            TokenType::Attribute => color_from_hex(self.special),
             TokenType::Comment(_) => color_from_hex(self.comments),
-            TokenType::Function => color_from_hex(self.functions),
-            TokenType::Keyword => color_from_hex(self.keywords),
+            TokenType::Function | TokenType::Macro => color_from_hex(self.functions),
+            TokenType::Keyword | TokenType::Lifetime => color_from_hex(self.keywords),
             TokenType::Literal => color_from_hex(self.literals),
-            TokenType::Hyperlink => color_from_hex(self.special),
+            TokenType::Hyperlink | TokenType::Interpolation => color_from_hex(self.special),
             TokenType::Numeric(_) => color_from_hex(self.numerics),
             TokenType::Punctuation(_) => color_from_hex(self.punctuation),
+            TokenType::Operator => color_from_hex(self.operators.unwrap_or(self.punctuation)),
             TokenType::Special => color_from_hex(self.special),
             TokenType::Str(_) => color_from_hex(self.strs),
+            TokenType::Escape => color_from_hex(self.escapes.unwrap_or(self.strs)),
             TokenType::Type => color_from_hex(self.types),
             TokenType::Whitespace(_) | TokenType::Unknown => color_from_hex(self.comments),
         }
@@ -159,6 +220,7 @@ impl ColorTheme {
             strs: fg,
             comments: fg,
             special: fg,
+            ..ColorTheme::DEFAULT
         }
     }
 }